@@ -0,0 +1,37 @@
+use std::{env, fs};
+
+use zk_kit_imt::{hash::keccak256_hash_function, imt::IMTMerkleProof};
+
+// Proof of the leaf "3" in a binary tree of depth 3 with the leaves "1", "2", "3".
+const PROOF: &str = r#"{
+    "root": "43fa3c2d12d8f12f9eaff493c888ba59f930e3ffac89384eaa7cbcf5d24409b5",
+    "leaf": "3",
+    "path_indices": [0, 1, 0],
+    "siblings": [
+        ["0"],
+        ["7f8b6b088b6d74c2852fc86c796dca07b44eed6fb3daf5e6b59f7c364db14528"],
+        ["12ffbd4b92cf90f62955e195d140b1127defa50801e710a8c4974e5c84eaee82"]
+    ]
+}"#;
+
+const ROOT: &str = "43fa3c2d12d8f12f9eaff493c888ba59f930e3ffac89384eaa7cbcf5d24409b5";
+
+// Usage: cargo run --example verify --features serde -- [proof.json] [root]
+fn main() {
+    let mut args = env::args().skip(1);
+
+    let json = match args.next() {
+        Some(path) => fs::read_to_string(path).expect("Cannot read the proof file"),
+        None => PROOF.to_string(),
+    };
+    let root = args.next().unwrap_or_else(|| ROOT.to_string());
+
+    // Deserialize the proof: the tree is not needed to verify it.
+    let proof: IMTMerkleProof = serde_json::from_str(&json).expect("Invalid proof");
+
+    // The proof must be valid and it must belong to the expected root.
+    let valid = proof.root == root && proof.verify(keccak256_hash_function);
+
+    println!("Leaf {:?} in tree {}: {}", proof.leaf, root, valid);
+    assert!(valid);
+}
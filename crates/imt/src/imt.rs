@@ -6,17 +6,19 @@ pub struct IMT {
     arity: usize,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IMTMerkleProof {
-    root: IMTNode,
-    leaf: IMTNode,
-    path_indices: Vec<usize>,
-    siblings: Vec<Vec<IMTNode>>,
+    pub root: IMTNode,
+    pub leaf: IMTNode,
+    pub path_indices: Vec<usize>,
+    pub siblings: Vec<Vec<IMTNode>>,
 }
 
 pub type IMTNode = String;
 pub type IMTHashFunction = fn(Vec<IMTNode>) -> IMTNode;
 
 impl IMT {
+    /// Creates a tree with the given leaves, `arity` must be at least 2 and `depth` at least 1.
     pub fn new(
         hash: IMTHashFunction,
         depth: usize,
@@ -24,24 +26,66 @@ impl IMT {
         arity: usize,
         leaves: Vec<IMTNode>,
     ) -> Result<IMT, &'static str> {
+        if arity < 2 {
+            return Err("The arity of the tree must be at least 2");
+        }
+
+        if depth == 0 {
+            return Err("The depth of the tree must be at least 1");
+        }
+
+        let mut zeroes = Vec::with_capacity(depth);
+        let mut current_zero = zero_value;
+        for _ in 0..depth {
+            zeroes.push(current_zero.clone());
+            current_zero = hash(vec![current_zero; arity]);
+        }
+
+        IMT::new_with_zeroes(hash, depth, zeroes, arity, leaves)
+    }
+
+    /// Creates a tree with the given zero node of each level, starting from the zero leaf,
+    /// instead of deriving them from a single zero value, e.g. to reuse precomputed zeroes.
+    /// `zeroes` must contain `depth` nodes, each one being the hash of `arity` zero nodes of the
+    /// level below, so that padding leaves and empty subtrees give the same roots.
+    pub fn new_with_zeroes(
+        hash: IMTHashFunction,
+        depth: usize,
+        zeroes: Vec<IMTNode>,
+        arity: usize,
+        leaves: Vec<IMTNode>,
+    ) -> Result<IMT, &'static str> {
+        if arity < 2 {
+            return Err("The arity of the tree must be at least 2");
+        }
+
+        if depth == 0 {
+            return Err("The depth of the tree must be at least 1");
+        }
+
+        if zeroes.len() != depth {
+            return Err("The tree must have a zero node for each level");
+        }
+
+        if zeroes
+            .windows(2)
+            .any(|pair| pair[1] != hash(vec![pair[0].clone(); arity]))
+        {
+            return Err("The zero nodes must be the hashes of the zero nodes of the level below");
+        }
+
         if leaves.len() > arity.pow(depth as u32) {
             return Err("The tree cannot contain more than arity^depth leaves");
         }
 
         let mut imt = IMT {
             nodes: vec![vec![]; depth + 1],
-            zeroes: vec![],
+            zeroes,
             hash,
             depth,
             arity,
         };
 
-        let mut current_zero = zero_value;
-        for _ in 0..depth {
-            imt.zeroes.push(current_zero.clone());
-            current_zero = (imt.hash)(vec![current_zero; arity]);
-        }
-
         imt.nodes[0] = leaves;
 
         for level in 0..depth {
@@ -65,7 +109,8 @@ impl IMT {
         Ok(imt)
     }
 
-    pub fn root(&mut self) -> Option<IMTNode> {
+    /// Returns the root of the tree, or `None` if the tree is empty.
+    pub fn root(&self) -> Option<IMTNode> {
         self.nodes[self.depth].first().cloned()
     }
 
@@ -73,40 +118,154 @@ impl IMT {
         self.depth
     }
 
+    /// Returns a copy of the nodes of the tree, prefer `nodes_ref` to read them without cloning.
     pub fn nodes(&self) -> Vec<Vec<IMTNode>> {
         self.nodes.clone()
     }
 
+    /// Returns the nodes of the tree, level by level starting from the leaves.
+    pub fn nodes_ref(&self) -> &[Vec<IMTNode>] {
+        &self.nodes
+    }
+
     pub fn zeroes(&self) -> Vec<IMTNode> {
         self.zeroes.clone()
     }
 
+    /// Returns a copy of the leaves of the tree, prefer `leaves_ref` or `leaves_iter` to read
+    /// them without cloning.
     pub fn leaves(&self) -> Vec<IMTNode> {
         self.nodes[0].clone()
     }
 
+    /// Returns the leaves of the tree.
+    pub fn leaves_ref(&self) -> &[IMTNode] {
+        &self.nodes[0]
+    }
+
+    /// Returns an iterator over the leaves of the tree.
+    pub fn leaves_iter(&self) -> impl Iterator<Item = &IMTNode> {
+        self.nodes[0].iter()
+    }
+
+    /// Returns the node at the given level (0 for the leaves) and index. Unfilled positions
+    /// return the zero node of the level, i.e. the root of an empty subtree.
+    pub fn get_node(&self, level: usize, index: usize) -> Result<IMTNode, &'static str> {
+        if level > self.depth {
+            return Err("The level does not exist in this tree");
+        }
+
+        if index >= self.arity.pow((self.depth - level) as u32) {
+            return Err("The index does not exist at this level");
+        }
+
+        Ok(self.nodes[level]
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| self.zero(level)))
+    }
+
+    /// Returns the siblings of the node at the given level and index, in order and without the
+    /// node itself, as in the proofs. Unfilled positions return the zero node of the level, and
+    /// the root has no siblings.
+    pub fn sibling(&self, level: usize, index: usize) -> Result<Vec<IMTNode>, &'static str> {
+        self.get_node(level, index)?;
+
+        if level == self.depth {
+            return Ok(vec![]);
+        }
+
+        let level_start_index = index - index % self.arity;
+
+        (level_start_index..level_start_index + self.arity)
+            .filter(|&i| i != index)
+            .map(|i| self.get_node(level, i))
+            .collect()
+    }
+
+    // Returns the root of an empty subtree at the given level.
+    fn zero(&self, level: usize) -> IMTNode {
+        if level < self.depth {
+            self.zeroes[level].clone()
+        } else {
+            (self.hash)(vec![self.zeroes[level - 1].clone(); self.arity])
+        }
+    }
+
     pub fn arity(&self) -> usize {
         self.arity
     }
 
+    /// Returns the maximum number of leaves of the tree, i.e. `arity^depth`.
+    pub fn capacity(&self) -> usize {
+        self.arity.pow(self.depth as u32)
+    }
+
+    /// Returns whether no more leaves can be appended with `insert`.
+    pub fn is_full(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Returns the number of leaves that can still be appended with `insert`.
+    pub fn remaining(&self) -> usize {
+        self.capacity() - self.nodes[0].len()
+    }
+
     pub fn insert(&mut self, leaf: IMTNode) -> Result<(), &'static str> {
-        if self.nodes[0].len() >= self.arity.pow(self.depth as u32) {
+        if self.is_full() {
             return Err("The tree is full");
         }
 
         let index = self.nodes[0].len();
         self.nodes[0].push(leaf);
-        self.update(index, self.nodes[0][index].clone())
+        self.update_path(index);
+
+        Ok(())
+    }
+
+    /// Inserts a leaf at the given empty (zero) index. The leaves before it that were not set are
+    /// filled with zeroes, so the next `insert` appends after the last set index.
+    pub fn insert_at(&mut self, index: usize, leaf: IMTNode) -> Result<(), &'static str> {
+        if index >= self.capacity() {
+            return Err("The index does not exist in this tree");
+        }
+
+        if self.nodes[0]
+            .get(index)
+            .is_some_and(|node| *node != self.zeroes[0])
+        {
+            return Err("The leaf already exists in this tree");
+        }
+
+        if index >= self.nodes[0].len() {
+            self.nodes[0].resize(index + 1, self.zeroes[0].clone());
+        }
+
+        self.nodes[0][index] = leaf;
+        self.update_path(index);
+
+        Ok(())
     }
 
-    pub fn update(&mut self, mut index: usize, new_leaf: IMTNode) -> Result<(), &'static str> {
+    /// Updates the leaf at the given index, the path to the root is not recomputed if the leaf
+    /// is unchanged.
+    pub fn update(&mut self, index: usize, new_leaf: IMTNode) -> Result<(), &'static str> {
         if index >= self.nodes[0].len() {
             return Err("The leaf does not exist in this tree");
         }
 
-        let mut node = new_leaf;
-        self.nodes[0][index].clone_from(&node);
+        if self.nodes[0][index] == new_leaf {
+            return Ok(());
+        }
+
+        self.nodes[0][index] = new_leaf;
+        self.update_path(index);
+
+        Ok(())
+    }
 
+    // Recomputes the nodes on the path from the leaf at the given index to the root.
+    fn update_path(&mut self, mut index: usize) {
         for level in 0..self.depth {
             let position = index % self.arity;
             let level_start_index = index - position;
@@ -121,17 +280,18 @@ impl IMT {
                 })
                 .collect();
 
-            node = (self.hash)(children);
+            let node = (self.hash)(children);
             index /= self.arity;
 
             if self.nodes[level + 1].len() <= index {
-                self.nodes[level + 1].push(node.clone());
+                // The skipped nodes, if any, are roots of empty subtrees.
+                let zero = self.zero(level + 1);
+                self.nodes[level + 1].resize(index, zero);
+                self.nodes[level + 1].push(node);
             } else {
-                self.nodes[level + 1][index].clone_from(&node);
+                self.nodes[level + 1][index] = node;
             }
         }
-
-        Ok(())
     }
 
     pub fn delete(&mut self, index: usize) -> Result<(), &'static str> {
@@ -178,17 +338,65 @@ impl IMT {
         })
     }
 
+    /// Creates the proofs of the leaves at the given indices, in the same order, fails if any
+    /// index is out of range.
+    pub fn create_proofs(&self, indices: &[usize]) -> Result<Vec<IMTMerkleProof>, &'static str> {
+        if indices.iter().any(|&index| index >= self.nodes[0].len()) {
+            return Err("The leaf does not exist in this tree");
+        }
+
+        indices
+            .iter()
+            .map(|&index| self.create_proof(index))
+            .collect()
+    }
+
     pub fn verify_proof(&self, proof: &IMTMerkleProof) -> bool {
-        let mut node = proof.leaf.clone();
+        proof.verify(self.hash)
+    }
+
+    /// Verifies the proof against its root with the given hash function, without a tree.
+    pub fn verify(proof: &IMTMerkleProof, hash: IMTHashFunction) -> bool {
+        proof.verify(hash)
+    }
+}
+
+impl IMTMerkleProof {
+    /// Creates a proof from its parts, e.g. to verify a proof received from elsewhere.
+    pub fn new(
+        root: IMTNode,
+        leaf: IMTNode,
+        path_indices: Vec<usize>,
+        siblings: Vec<Vec<IMTNode>>,
+    ) -> IMTMerkleProof {
+        IMTMerkleProof {
+            root,
+            leaf,
+            path_indices,
+            siblings,
+        }
+    }
+
+    /// Verifies the proof against its root with the given hash function, without the tree.
+    pub fn verify(&self, hash: IMTHashFunction) -> bool {
+        if self.path_indices.len() != self.siblings.len() {
+            return false;
+        }
+
+        let mut node = self.leaf.clone();
+
+        for (i, sibling) in self.siblings.iter().enumerate() {
+            if self.path_indices[i] > sibling.len() {
+                return false;
+            }
 
-        for (i, sibling) in proof.siblings.iter().enumerate() {
             let mut children = sibling.clone();
-            children.insert(proof.path_indices[i], node);
+            children.insert(self.path_indices[i], node);
 
-            node = (self.hash)(children);
+            node = hash(children);
         }
 
-        node == proof.root
+        node == self.root
     }
 }
 
@@ -208,6 +416,71 @@ mod tests {
         assert!(imt.is_ok());
     }
 
+    #[test]
+    fn test_new_imt_invalid_parameters() {
+        let hash: IMTHashFunction = simple_hash_function;
+
+        for arity in [0, 1] {
+            let imt = IMT::new(hash, 3, "zero".to_string(), arity, vec![]);
+
+            assert_eq!(imt.err(), Some("The arity of the tree must be at least 2"));
+        }
+
+        let imt = IMT::new(hash, 0, "zero".to_string(), 2, vec![]);
+
+        assert_eq!(imt.err(), Some("The depth of the tree must be at least 1"));
+    }
+
+    #[test]
+    fn test_new_with_zeroes() {
+        let hash: IMTHashFunction = simple_hash_function;
+        let zeroes = vec!["z".to_string(), "z,z".to_string(), "z,z,z,z".to_string()];
+        let mut imt = IMT::new_with_zeroes(hash, 3, zeroes.clone(), 2, vec![]).unwrap();
+
+        assert_eq!(imt.zeroes(), zeroes);
+        assert_eq!(
+            imt.root(),
+            IMT::new(hash, 3, "z".to_string(), 2, vec![])
+                .unwrap()
+                .root()
+        );
+
+        imt.insert("leaf0".to_string()).unwrap();
+        imt.insert("leaf1".to_string()).unwrap();
+        assert_eq!(imt.root(), Some("leaf0,leaf1,z,z,z,z,z,z".to_string()));
+
+        let proof = imt.create_proof(1).unwrap();
+        assert_eq!(
+            proof.siblings,
+            vec![
+                vec!["leaf0".to_string()],
+                vec!["z,z".to_string()],
+                vec!["z,z,z,z".to_string()]
+            ]
+        );
+        assert!(imt.verify_proof(&proof));
+
+        // Inserting the zero leaf pads the tree without changing the root.
+        let root = imt.root();
+        imt.insert("z".to_string()).unwrap();
+        assert_eq!(imt.root(), root);
+        assert!(imt.verify_proof(&imt.create_proof(2).unwrap()));
+
+        let imt = IMT::new_with_zeroes(hash, 3, zeroes[..2].to_vec(), 2, vec![]);
+        assert_eq!(
+            imt.err(),
+            Some("The tree must have a zero node for each level")
+        );
+
+        let mut other_zeroes = zeroes.clone();
+        other_zeroes[1] = "z1".to_string();
+        let imt = IMT::new_with_zeroes(hash, 3, other_zeroes, 2, vec![]);
+        assert_eq!(
+            imt.err(),
+            Some("The zero nodes must be the hashes of the zero nodes of the level below")
+        );
+    }
+
     #[test]
     fn test_insertion() {
         let hash: IMTHashFunction = simple_hash_function;
@@ -216,6 +489,70 @@ mod tests {
         assert!(imt.insert("leaf1".to_string()).is_ok());
     }
 
+    #[test]
+    fn test_insert_at() {
+        let hash: IMTHashFunction = simple_hash_function;
+        let mut imt = IMT::new(hash, 2, "zero".to_string(), 3, vec![]).unwrap();
+
+        imt.insert_at(7, "leaf7".to_string()).unwrap();
+        imt.insert_at(1, "leaf1".to_string()).unwrap();
+        imt.insert_at(4, "leaf4".to_string()).unwrap();
+        imt.insert_at(0, "leaf0".to_string()).unwrap();
+
+        let mut expected = IMT::new(hash, 2, "zero".to_string(), 3, vec![]).unwrap();
+        for i in 0..8 {
+            let leaf = match i {
+                0 | 1 | 4 | 7 => format!("leaf{}", i),
+                _ => "zero".to_string(),
+            };
+            expected.insert(leaf).unwrap();
+        }
+        assert_eq!(imt.nodes, expected.nodes);
+
+        for index in [0, 1, 4, 7] {
+            let proof = imt.create_proof(index).unwrap();
+            assert_eq!(proof.leaf, format!("leaf{}", index));
+            assert!(imt.verify_proof(&proof));
+        }
+
+        assert_eq!(
+            imt.insert_at(4, "other".to_string()),
+            Err("The leaf already exists in this tree")
+        );
+        assert_eq!(
+            imt.insert_at(9, "leaf9".to_string()),
+            Err("The index does not exist in this tree")
+        );
+
+        imt.insert("leaf8".to_string()).unwrap();
+        assert_eq!(imt.leaves()[8], "leaf8");
+        assert_eq!(imt.insert("leaf9".to_string()), Err("The tree is full"));
+
+        imt.insert_at(2, "leaf2".to_string()).unwrap();
+        assert!(imt.verify_proof(&imt.create_proof(2).unwrap()));
+    }
+
+    #[test]
+    fn test_capacity() {
+        let hash: IMTHashFunction = simple_hash_function;
+        let mut imt = IMT::new(hash, 2, "zero".to_string(), 2, vec![]).unwrap();
+
+        assert_eq!(imt.capacity(), 4);
+        assert_eq!(imt.remaining(), 4);
+
+        for i in 0..4 {
+            assert!(!imt.is_full());
+            imt.insert(format!("leaf{}", i)).unwrap();
+            assert_eq!(imt.remaining(), 3 - i);
+        }
+
+        assert!(imt.is_full());
+        assert_eq!(imt.insert("leaf4".to_string()), Err("The tree is full"));
+
+        let imt = IMT::new(hash, 3, "zero".to_string(), 3, vec![]).unwrap();
+        assert_eq!(imt.capacity(), 27);
+    }
+
     #[test]
     fn test_delete() {
         let hash: IMTHashFunction = simple_hash_function;
@@ -232,6 +569,52 @@ mod tests {
         assert!(imt.update(0, "new_leaf".to_string()).is_ok());
     }
 
+    #[test]
+    fn test_update_unchanged_leaf() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static HASH_COUNT: Cell<usize> = const { Cell::new(0) };
+        }
+
+        fn counting_hash_function(nodes: Vec<String>) -> String {
+            HASH_COUNT.with(|count| count.set(count.get() + 1));
+            nodes.join(",")
+        }
+
+        let hash: IMTHashFunction = counting_hash_function;
+        let mut imt = IMT::new(
+            hash,
+            3,
+            "zero".to_string(),
+            2,
+            vec!["leaf1".to_string(), "leaf2".to_string()],
+        )
+        .unwrap();
+        let nodes = imt.nodes();
+        let root = imt.root();
+
+        HASH_COUNT.with(|count| count.set(0));
+        assert!(imt.update(1, "leaf2".to_string()).is_ok());
+        assert_eq!(HASH_COUNT.with(Cell::get), 0);
+        assert_eq!(imt.nodes(), nodes);
+        assert_eq!(imt.root(), root);
+
+        assert!(imt.update(1, "leaf3".to_string()).is_ok());
+        assert_eq!(HASH_COUNT.with(Cell::get), 3);
+        assert_eq!(
+            imt.root(),
+            Some("leaf1,leaf3,zero,zero,zero,zero,zero,zero".to_string())
+        );
+
+        assert!(imt.insert("leaf4".to_string()).is_ok());
+        assert_eq!(imt.leaves(), vec!["leaf1", "leaf3", "leaf4"]);
+        assert_eq!(
+            imt.root(),
+            Some("leaf1,leaf3,leaf4,zero,zero,zero,zero,zero".to_string())
+        );
+    }
+
     #[test]
     fn test_create_and_verify_proof() {
         let hash: IMTHashFunction = simple_hash_function;
@@ -245,6 +628,177 @@ mod tests {
         assert!(imt.verify_proof(&proof));
     }
 
+    #[test]
+    fn test_verify_proof_without_tree() {
+        let hash: IMTHashFunction = simple_hash_function;
+        let imt = IMT::new(
+            hash,
+            2,
+            "zero".to_string(),
+            3,
+            vec!["leaf1".to_string(), "leaf2".to_string()],
+        )
+        .unwrap();
+
+        let mut proof = imt.create_proof(1).unwrap();
+        assert!(proof.verify(hash));
+
+        proof.leaf = "leaf3".to_string();
+        assert!(!proof.verify(hash));
+    }
+
+    #[test]
+    fn test_verify_standalone() {
+        let hash: IMTHashFunction = simple_hash_function;
+
+        // Binary tree of depth 2 with leaves a, b, c.
+        let proof = IMTMerkleProof::new(
+            "a,b,c,zero".to_string(),
+            "b".to_string(),
+            vec![1, 0],
+            vec![vec!["a".to_string()], vec!["c,zero".to_string()]],
+        );
+        assert!(IMT::verify(&proof, hash));
+
+        let imt = IMT::new(
+            hash,
+            2,
+            "zero".to_string(),
+            2,
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        )
+        .unwrap();
+        assert!(imt.verify_proof(&proof));
+        assert_eq!(proof.siblings, imt.create_proof(1).unwrap().siblings);
+
+        // Ternary tree of depth 2 with leaves a, b, c, d.
+        let proof = IMTMerkleProof::new(
+            "a,b,c,d,zero,zero,zero,zero,zero".to_string(),
+            "d".to_string(),
+            vec![0, 1],
+            vec![
+                vec!["zero".to_string(), "zero".to_string()],
+                vec!["a,b,c".to_string(), "zero,zero,zero".to_string()],
+            ],
+        );
+        assert!(IMT::verify(&proof, hash));
+
+        let mut wrong_proof = IMTMerkleProof::new(
+            proof.root.clone(),
+            proof.leaf.clone(),
+            vec![1, 1],
+            proof.siblings.clone(),
+        );
+        assert!(!IMT::verify(&wrong_proof, hash));
+
+        wrong_proof.path_indices = vec![0];
+        assert!(!IMT::verify(&wrong_proof, hash));
+    }
+
+    #[test]
+    fn test_get_node_and_sibling() {
+        let hash: IMTHashFunction = simple_hash_function;
+        let leaves: Vec<String> = (0..4).map(|i| format!("leaf{}", i)).collect();
+        let imt = IMT::new(hash, 2, "zero".to_string(), 3, leaves).unwrap();
+
+        assert_eq!(imt.get_node(0, 1), Ok("leaf1".to_string()));
+        assert_eq!(imt.get_node(0, 3), Ok("leaf3".to_string()));
+        assert_eq!(imt.get_node(1, 0), Ok("leaf0,leaf1,leaf2".to_string()));
+        assert_eq!(imt.get_node(1, 1), Ok("leaf3,zero,zero".to_string()));
+        assert_eq!(imt.get_node(2, 0).ok(), imt.root());
+
+        // Unfilled positions hold the zero of their level.
+        assert_eq!(imt.get_node(0, 8), Ok("zero".to_string()));
+        assert_eq!(imt.get_node(1, 2), Ok("zero,zero,zero".to_string()));
+
+        assert_eq!(
+            imt.get_node(0, 9),
+            Err("The index does not exist at this level")
+        );
+        assert_eq!(
+            imt.get_node(2, 1),
+            Err("The index does not exist at this level")
+        );
+        assert_eq!(
+            imt.get_node(3, 0),
+            Err("The level does not exist in this tree")
+        );
+
+        assert_eq!(
+            imt.sibling(0, 3),
+            Ok(vec!["zero".to_string(), "zero".to_string()])
+        );
+        assert_eq!(
+            imt.sibling(1, 0),
+            Ok(vec![
+                "leaf3,zero,zero".to_string(),
+                "zero,zero,zero".to_string()
+            ])
+        );
+        assert_eq!(imt.sibling(2, 0), Ok(vec![]));
+        assert_eq!(
+            imt.sibling(1, 3),
+            Err("The index does not exist at this level")
+        );
+
+        let proof = imt.create_proof(1).unwrap();
+        assert_eq!(imt.sibling(0, 1).unwrap(), proof.siblings[0]);
+        assert_eq!(imt.sibling(1, 0).unwrap(), proof.siblings[1]);
+
+        let empty_imt = IMT::new(hash, 1, "zero".to_string(), 2, vec![]).unwrap();
+        assert_eq!(empty_imt.get_node(1, 0), Ok("zero,zero".to_string()));
+    }
+
+    #[test]
+    fn test_create_proofs() {
+        let hash: IMTHashFunction = simple_hash_function;
+        let leaves: Vec<String> = (0..5).map(|i| format!("leaf{}", i)).collect();
+        let imt = IMT::new(hash, 2, "zero".to_string(), 3, leaves).unwrap();
+
+        let indices = [4, 0, 2, 4];
+        let proofs = imt.create_proofs(&indices).unwrap();
+
+        assert_eq!(proofs.len(), indices.len());
+
+        for (&index, proof) in indices.iter().zip(&proofs) {
+            let expected = imt.create_proof(index).unwrap();
+
+            assert_eq!(proof.root, expected.root);
+            assert_eq!(proof.leaf, expected.leaf);
+            assert_eq!(proof.path_indices, expected.path_indices);
+            assert_eq!(proof.siblings, expected.siblings);
+            assert!(imt.verify_proof(proof));
+        }
+
+        assert!(imt.create_proofs(&[]).unwrap().is_empty());
+        assert_eq!(
+            imt.create_proofs(&[0, 5]).err(),
+            Some("The leaf does not exist in this tree")
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_proof_serialization() {
+        let hash: IMTHashFunction = simple_hash_function;
+        let imt = IMT::new(
+            hash,
+            3,
+            "zero".to_string(),
+            2,
+            vec!["leaf1".to_string(), "leaf2".to_string()],
+        )
+        .unwrap();
+
+        let proof = imt.create_proof(1).unwrap();
+        let json = serde_json::to_string(&proof).unwrap();
+        let proof: IMTMerkleProof = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(proof.leaf, "leaf2");
+        assert!(proof.verify(hash));
+        assert!(imt.verify_proof(&proof));
+    }
+
     #[test]
     fn should_not_initialize_with_too_many_leaves() {
         let hash: IMTHashFunction = simple_hash_function;
@@ -287,7 +841,7 @@ mod tests {
     #[test]
     fn test_root() {
         let hash: IMTHashFunction = simple_hash_function;
-        let mut imt = IMT::new(
+        let imt = IMT::new(
             hash,
             2,
             "zero".to_string(),
@@ -296,7 +850,11 @@ mod tests {
         )
         .unwrap();
 
-        assert_eq!(imt.root(), Some("leaf1,leaf2,zero,zero".to_string()));
+        let shared: &IMT = &imt;
+        assert_eq!(shared.root(), Some("leaf1,leaf2,zero,zero".to_string()));
+
+        let empty = IMT::new(hash, 2, "zero".to_string(), 2, vec![]).unwrap();
+        assert_eq!(empty.root(), None);
     }
 
     #[test]
@@ -314,6 +872,28 @@ mod tests {
         assert_eq!(imt.leaves(), vec!["leaf1".to_string(), "leaf2".to_string()]);
     }
 
+    #[test]
+    fn test_borrowed_accessors() {
+        let hash: IMTHashFunction = simple_hash_function;
+        let mut imt = IMT::new(hash, 2, "zero".to_string(), 2, vec!["leaf1".to_string()]).unwrap();
+
+        assert_eq!(imt.leaves_ref(), &["leaf1".to_string()]);
+
+        imt.insert("leaf2".to_string()).unwrap();
+
+        assert_eq!(imt.leaves_ref(), imt.leaves().as_slice());
+        assert_eq!(
+            imt.leaves_iter().collect::<Vec<_>>(),
+            vec!["leaf1", "leaf2"]
+        );
+        assert_eq!(imt.nodes_ref(), imt.nodes().as_slice());
+        assert_eq!(imt.nodes_ref()[1], vec!["leaf1,leaf2".to_string()]);
+        assert_eq!(
+            imt.nodes_ref()[2],
+            vec!["leaf1,leaf2,zero,zero".to_string()]
+        );
+    }
+
     #[test]
     fn test_depth_and_arity() {
         let hash: IMTHashFunction = simple_hash_function;
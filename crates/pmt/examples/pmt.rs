@@ -46,6 +46,8 @@ impl Database for MemoryDB {
 impl Hasher for MyKeccak {
     type Fr = [u8; 32];
 
+    const WIDTH: usize = 32;
+
     fn default_leaf() -> Self::Fr {
         [0; 32]
     }
@@ -20,12 +20,37 @@ pub trait Database {
     /// Returns value from db by the key
     fn get(&self, key: DBKey) -> PmtreeResult<Option<Value>>;
 
+    /// Returns values from db by the keys, in the same order.
+    /// Default implementation calls `get` for each key, backends supporting multi-get should override it
+    fn get_batch(&self, keys: &[DBKey]) -> PmtreeResult<Vec<Option<Value>>> {
+        keys.iter().map(|&key| self.get(key)).collect()
+    }
+
     /// Puts the value to the db by the key
     fn put(&mut self, key: DBKey, value: Value) -> PmtreeResult<()>;
 
     /// Puts the leaves batch to the db
     fn put_batch(&mut self, subtree: HashMap<DBKey, Value>) -> PmtreeResult<()>;
 
+    /// Returns an iterator over all the key-value pairs of the db, in no particular order.
+    /// Default implementation fails, backends supporting scans should override it
+    fn iter(&self) -> PmtreeResult<Box<dyn Iterator<Item = (DBKey, Value)> + '_>> {
+        Err(PmtreeErrorKind::DatabaseError(
+            DatabaseErrorKind::CustomError("iteration unsupported".to_string()),
+        ))
+    }
+
+    /// Returns an independent copy of the db, later writes to either of them do not affect the other.
+    /// Default implementation fails with `CannotLoadDatabase`, backends supporting snapshots should override it
+    fn snapshot(&self) -> PmtreeResult<Self>
+    where
+        Self: Sized,
+    {
+        Err(PmtreeErrorKind::DatabaseError(
+            DatabaseErrorKind::CannotLoadDatabase,
+        ))
+    }
+
     /// Closes the db connection
     fn close(&mut self) -> PmtreeResult<()>;
 }
@@ -7,6 +7,9 @@ pub trait Hasher {
     /// Native type for the hash-function
     type Fr: Copy + Eq + Default + Sync + Send + Debug;
 
+    /// Width in bytes of a serialized Self::Fr
+    const WIDTH: usize;
+
     /// Serializes Self::Fr
     fn serialize(value: Self::Fr) -> Value;
 
@@ -20,4 +23,12 @@ pub trait Hasher {
 
     /// Calculates hash-function
     fn hash(input: &[Self::Fr]) -> Self::Fr;
+
+    /// Maps a leaf to the leaf node stored in the tree (identity by default).
+    ///
+    /// Override it to hash leaves in a different domain than internal nodes (e.g. by prefixing
+    /// the input with a tag), so that an internal node cannot be presented as a leaf.
+    fn hash_leaf(leaf: Self::Fr) -> Self::Fr {
+        leaf
+    }
 }
@@ -1,7 +1,7 @@
 use crate::*;
 
 use std::cmp::{max, min};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::{Arc, RwLock};
 
 // db[DEPTH_KEY] = depth
@@ -10,6 +10,9 @@ const DEPTH_KEY: DBKey = (u64::MAX - 1).to_be_bytes();
 // db[NEXT_INDEX_KEY] = next_index;
 const NEXT_INDEX_KEY: DBKey = u64::MAX.to_be_bytes();
 
+// db[DOMAIN_KEY] = domain, if any
+const DOMAIN_KEY: DBKey = (u64::MAX - 2).to_be_bytes();
+
 // Default tree depth
 const DEFAULT_TREE_DEPTH: usize = 20;
 
@@ -25,6 +28,17 @@ impl From<Key> for DBKey {
 }
 
 /// The Merkle Tree structure
+///
+/// Leaves are not stored as they are: the leaf layer holds the leaf nodes `H::hash_leaf(leaf)`,
+/// while every internal node is `H::hash(&[left, right])`. Empty leaves hold `H::default_leaf()`.
+/// A leaf that differs from its leaf node is also stored, one level below the leaf layer, so that
+/// `get` returns it.
+/// A tree created with a domain hashes internal nodes as `H::hash(&[domain, left, right])`
+/// instead, so trees with different domains are separated even with the same hasher.
+///
+/// The nodes of empty subtrees are not persisted: a node absent from the db is the root of an
+/// empty subtree, which is derived from `H::default_leaf()`. Writing the root of an empty subtree,
+/// e.g. when deleting a leaf, thus costs a db read to know whether a persisted node is overwritten.
 pub struct MerkleTree<D, H>
 where
     D: Database,
@@ -35,6 +49,8 @@ where
     next_index: usize,
     cache: Vec<H::Fr>,
     root: H::Fr,
+    leaf_index: Option<HashMap<Value, BTreeSet<usize>>>,
+    domain: Option<H::Fr>,
 }
 
 /// The Merkle proof structure
@@ -53,6 +69,18 @@ where
 
     /// Creates new `MerkleTree` and store it to the specified path/db
     pub fn new(depth: usize, db_config: D::Config) -> PmtreeResult<Self> {
+        Self::new_with_domain(depth, db_config, None)
+    }
+
+    /// Creates new `MerkleTree` whose internal nodes are hashed with the given domain, if any.
+    /// The domain is stored in the db, so `load` restores it
+    pub fn new_with_domain(
+        depth: usize,
+        db_config: D::Config,
+        domain: Option<H::Fr>,
+    ) -> PmtreeResult<Self> {
+        Self::check_hasher_width()?;
+
         // Create new db instance
         let mut db = D::new(db_config)?;
 
@@ -65,15 +93,15 @@ where
         let next_index_val = next_index.to_be_bytes().to_vec();
         db.put(NEXT_INDEX_KEY, next_index_val)?;
 
-        // Cache nodes
-        let mut cache = vec![H::default_leaf(); depth + 1];
+        // Insert domain val into db
+        if let Some(domain) = domain {
+            db.put(DOMAIN_KEY, H::serialize(domain))?;
+        }
 
-        // Initialize one branch of the `Merkle Tree` from bottom to top
-        cache[depth] = H::default_leaf();
-        db.put(Key(depth, 0).into(), H::serialize(cache[depth]))?;
+        // Cache the roots of the empty subtrees, which are not persisted
+        let mut cache = vec![H::default_leaf(); depth + 1];
         for i in (0..depth).rev() {
-            cache[i] = H::hash(&[cache[i + 1], cache[i + 1]]);
-            db.put(Key(i, 0).into(), H::serialize(cache[i]))?;
+            cache[i] = hash_node::<H>(domain, cache[i + 1], cache[i + 1]);
         }
 
         let root = cache[0];
@@ -84,20 +112,18 @@ where
             next_index,
             cache,
             root,
+            leaf_index: None,
+            domain,
         })
     }
 
     /// Loads existing Merkle Tree from the specified path/db
     pub fn load(db_config: D::Config) -> PmtreeResult<Self> {
+        Self::check_hasher_width()?;
+
         // Load existing db instance
         let db = D::load(db_config)?;
 
-        // Load root
-        let root = match db.get(Key(0, 0).into())? {
-            Some(root) => H::deserialize(root),
-            None => H::default_leaf(),
-        };
-
         // Load depth & next_index values from db
         let depth = match db.get(DEPTH_KEY)? {
             Some(depth) => usize::from_be_bytes(depth.try_into().unwrap()),
@@ -109,22 +135,69 @@ where
             None => 0,
         };
 
+        let domain = db.get(DOMAIN_KEY)?.map(H::deserialize);
+
         // Load cache vec
         let mut cache = vec![H::default_leaf(); depth + 1];
-        cache[depth] = H::default_leaf();
         for i in (0..depth).rev() {
-            cache[i] = H::hash(&[cache[i + 1], cache[i + 1]]);
+            cache[i] = hash_node::<H>(domain, cache[i + 1], cache[i + 1]);
         }
 
+        // Load root, the tree is empty if it is not persisted
+        let root = match db.get(Key(0, 0).into())? {
+            Some(root) => H::deserialize(root),
+            None => cache[0],
+        };
+
         Ok(Self {
             db,
             depth,
             next_index,
             cache,
             root,
+            leaf_index: None,
+            domain,
         })
     }
 
+    /// Returns all the key-value pairs of the db sorted by key, including the tree metadata.
+    /// Fails if the db does not support iteration (see `Database::iter`)
+    pub fn dump(&self) -> PmtreeResult<Vec<(DBKey, Value)>> {
+        let mut entries: Vec<_> = self.db.iter()?.collect();
+        entries.sort_unstable_by_key(|&(key, _)| key);
+
+        Ok(entries)
+    }
+
+    /// Forks the tree into an independent tree backed by a snapshot of the db.
+    /// Fails if the db does not support snapshots (see `Database::snapshot`)
+    pub fn fork(&self) -> PmtreeResult<Self> {
+        Ok(Self {
+            db: self.db.snapshot()?,
+            depth: self.depth,
+            next_index: self.next_index,
+            cache: self.cache.clone(),
+            root: self.root,
+            leaf_index: self.leaf_index.clone(),
+            domain: self.domain,
+        })
+    }
+
+    // Checks that the hasher serializes values with its declared width
+    fn check_hasher_width() -> PmtreeResult<()> {
+        let width = H::serialize(H::default_leaf()).len();
+        if width != H::WIDTH {
+            return Err(PmtreeErrorKind::DatabaseError(
+                DatabaseErrorKind::CustomError(format!(
+                    "Hasher serializes values to {width} bytes instead of {}",
+                    H::WIDTH
+                )),
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Closes the db connection
     pub fn close(&mut self) -> PmtreeResult<()> {
         self.db.close()
@@ -132,12 +205,30 @@ where
 
     /// Sets a leaf at the specified tree index
     pub fn set(&mut self, key: usize, leaf: H::Fr) -> PmtreeResult<()> {
+        let node = H::hash_leaf(leaf);
+        self.set_leaf_node(key, node)?;
+
+        if node != leaf {
+            self.db.put(self.leaf_key(key), H::serialize(leaf))?;
+        }
+
+        Ok(())
+    }
+
+    // Returns the db key of the leaf stored next to the leaf node at `index`
+    fn leaf_key(&self, index: usize) -> DBKey {
+        Key(self.depth + 1, index).into()
+    }
+
+    // Sets a leaf node at the specified tree index
+    fn set_leaf_node(&mut self, key: usize, node: H::Fr) -> PmtreeResult<()> {
         if key >= self.capacity() {
             return Err(PmtreeErrorKind::TreeError(TreeErrorKind::IndexOutOfBounds));
         }
 
-        self.db
-            .put(Key(self.depth, key).into(), H::serialize(leaf))?;
+        self.index_leaf_node(key, node)?;
+
+        self.put_node(Key(self.depth, key), node)?;
         self.recalculate_from(key)?;
 
         // Update next_index in memory
@@ -150,6 +241,32 @@ where
         Ok(())
     }
 
+    // Updates the value->index map (if enabled) before the leaf node at `key` is overwritten
+    fn index_leaf_node(&mut self, key: usize, node: H::Fr) -> PmtreeResult<()> {
+        if self.leaf_index.is_none() {
+            return Ok(());
+        }
+
+        let old_node = H::serialize(self.get_elem(Key(self.depth, key))?);
+        let leaf_index = self.leaf_index.as_mut().unwrap();
+
+        if let Some(indexes) = leaf_index.get_mut(&old_node) {
+            indexes.remove(&key);
+            if indexes.is_empty() {
+                leaf_index.remove(&old_node);
+            }
+        }
+
+        if node != H::default_leaf() {
+            leaf_index
+                .entry(H::serialize(node))
+                .or_default()
+                .insert(key);
+        }
+
+        Ok(())
+    }
+
     // Recalculates `Merkle Tree` from the specified key
     fn recalculate_from(&mut self, key: usize) -> PmtreeResult<()> {
         let mut depth = self.depth;
@@ -159,7 +276,7 @@ where
             let value = self.hash_couple(depth, i)?;
             i >>= 1;
             depth -= 1;
-            self.db.put(Key(depth, i).into(), H::serialize(value))?;
+            self.put_node(Key(depth, i), value)?;
 
             if depth == 0 {
                 self.root = value;
@@ -170,13 +287,41 @@ where
         Ok(())
     }
 
+    // Persists a node, unless it is the root of an empty subtree that is not persisted yet.
+    // Writing such a root, e.g. when deleting, costs a db read to know if it overwrites a node
+    fn put_node(&mut self, key: Key, value: H::Fr) -> PmtreeResult<()> {
+        if value == self.cache[key.0] && self.db.get(key.into())?.is_none() {
+            return Ok(());
+        }
+
+        self.db.put(key.into(), H::serialize(value))
+    }
+
+    // Adds a node to a batch, unless it is the root of an empty subtree that is not persisted yet.
+    // Like in `put_node`, only the roots of empty subtrees cost a db read
+    fn batch_node(
+        &self,
+        batch: &mut HashMap<DBKey, Value>,
+        key: Key,
+        value: H::Fr,
+    ) -> PmtreeResult<()> {
+        if value == self.cache[key.0] && self.db.get(key.into())?.is_none() {
+            return Ok(());
+        }
+
+        batch.insert(key.into(), H::serialize(value));
+
+        Ok(())
+    }
+
     // Hashes the correct couple for the key
     fn hash_couple(&self, depth: usize, key: usize) -> PmtreeResult<H::Fr> {
         let b = key & !1;
-        Ok(H::hash(&[
+        Ok(hash_node::<H>(
+            self.domain,
             self.get_elem(Key(depth, b))?,
             self.get_elem(Key(depth, b + 1))?,
-        ]))
+        ))
     }
 
     // Returns elem by the key
@@ -195,7 +340,7 @@ where
             return Err(PmtreeErrorKind::TreeError(TreeErrorKind::InvalidKey));
         }
 
-        self.set(key, H::default_leaf())?;
+        self.set_leaf_node(key, H::default_leaf())?;
 
         Ok(())
     }
@@ -207,6 +352,124 @@ where
         Ok(())
     }
 
+    /// Returns the frontier of the tree: for each level starting from the leaves, the left
+    /// sibling of the path of the next index, or the empty subtree node if there is none
+    pub fn frontier(&self) -> PmtreeResult<Vec<H::Fr>> {
+        let mut frontier = Vec::with_capacity(self.depth);
+        let mut index = self.next_index;
+
+        for level in (1..=self.depth).rev() {
+            if index & 1 == 1 {
+                frontier.push(self.get_elem(Key(level, index - 1))?);
+            } else {
+                frontier.push(self.cache[level]);
+            }
+            index >>= 1;
+        }
+
+        Ok(frontier)
+    }
+
+    /// Inserts a leaf to the next available index like `update_next`, but computes its path from
+    /// the given frontier (see `frontier`) instead of reading the siblings from the db.
+    /// The frontier is updated in place for the next append. A frontier that does not match the
+    /// current root, e.g. one taken before another insertion, is rejected
+    pub fn append_with_frontier(
+        &mut self,
+        frontier: &mut [H::Fr],
+        leaf: H::Fr,
+    ) -> PmtreeResult<()> {
+        if frontier.len() != self.depth {
+            return Err(PmtreeErrorKind::TreeError(TreeErrorKind::CustomError(
+                format!(
+                    "Frontier has {} nodes instead of {}",
+                    frontier.len(),
+                    self.depth
+                ),
+            )));
+        }
+
+        if self.next_index >= self.capacity() {
+            return Err(PmtreeErrorKind::TreeError(TreeErrorKind::MerkleTreeIsFull));
+        }
+
+        let index = self.next_index;
+
+        // The leaves from the next index on are empty, so the frontier of the tree gives its root
+        let mut node = self.cache[self.depth];
+        for (height, level) in (1..=self.depth).rev().enumerate() {
+            node = if (index >> height) & 1 == 1 {
+                hash_node::<H>(self.domain, frontier[height], node)
+            } else {
+                hash_node::<H>(self.domain, node, self.cache[level])
+            };
+        }
+        if node != self.root {
+            return Err(PmtreeErrorKind::TreeError(TreeErrorKind::CustomError(
+                "Frontier does not match the tree".to_string(),
+            )));
+        }
+
+        let mut node = H::hash_leaf(leaf);
+        self.index_leaf_node(index, node)?;
+
+        let mut batch = HashMap::with_capacity(self.depth + 2);
+        if node != leaf {
+            batch.insert(self.leaf_key(index), H::serialize(leaf));
+        }
+        for (height, level) in (1..=self.depth).rev().enumerate() {
+            let key = index >> height;
+            self.batch_node(&mut batch, Key(level, key), node)?;
+
+            // The left sibling of the path of the next index at this level is either unchanged,
+            // the node of the current path, or absent
+            let next_key = (index + 1) >> height;
+            let frontier_node = frontier[height];
+            if next_key & 1 == 0 {
+                frontier[height] = self.cache[level];
+            } else if next_key - 1 == key {
+                frontier[height] = node;
+            }
+
+            // The right siblings of the path are empty subtrees, since it is the next index
+            node = if key & 1 == 1 {
+                hash_node::<H>(self.domain, frontier_node, node)
+            } else {
+                hash_node::<H>(self.domain, node, self.cache[level])
+            };
+        }
+        self.batch_node(&mut batch, Key(0, 0), node)?;
+
+        self.db.put_batch(batch)?;
+
+        self.root = node;
+        self.next_index = index + 1;
+        self.db
+            .put(NEXT_INDEX_KEY, self.next_index.to_be_bytes().to_vec())?;
+
+        Ok(())
+    }
+
+    /// Sets the index used by `update_next` and `batch_insert` for the next insertion, e.g. when
+    /// resuming a sync from a checkpoint. Moving the cursor back is only allowed over empty leaves
+    pub fn set_next_index(&mut self, index: usize) -> PmtreeResult<()> {
+        if index > self.capacity() {
+            return Err(PmtreeErrorKind::TreeError(TreeErrorKind::IndexOutOfBounds));
+        }
+
+        for key in index..self.next_index {
+            if self.get_elem(Key(self.depth, key))? != H::default_leaf() {
+                return Err(PmtreeErrorKind::TreeError(TreeErrorKind::InvalidKey));
+            }
+        }
+
+        self.next_index = index;
+        self.db
+            .put(NEXT_INDEX_KEY, self.next_index.to_be_bytes().to_vec())?;
+
+        Ok(())
+    }
+
     /// Batch insertion from starting index
     pub fn set_range<I: IntoIterator<Item = H::Fr>>(
         &mut self,
@@ -228,12 +491,22 @@ where
             return Err(PmtreeErrorKind::TreeError(TreeErrorKind::MerkleTreeIsFull));
         }
 
+        let raw_leaves = leaves;
+        let leaves = leaves
+            .iter()
+            .map(|&leaf| H::hash_leaf(leaf))
+            .collect::<Vec<_>>();
+
+        for (i, &node) in leaves.iter().enumerate() {
+            self.index_leaf_node(start + i, node)?;
+        }
+
         let mut subtree = HashMap::<Key, H::Fr>::new();
 
         let root_key = Key(0, 0);
 
         subtree.insert(root_key, self.root);
-        self.fill_nodes(root_key, start, end, &mut subtree, leaves, start)?;
+        self.fill_nodes(root_key, start, end, &mut subtree, &leaves, start)?;
 
         let subtree = Arc::new(RwLock::new(subtree));
 
@@ -241,16 +514,39 @@ where
             .num_threads(rayon::current_num_threads())
             .build()
             .unwrap()
-            .install(|| Self::batch_recalculate(root_key, Arc::clone(&subtree), self.depth));
+            .install(|| {
+                Self::batch_recalculate(root_key, Arc::clone(&subtree), self.depth, self.domain)
+            });
 
         let subtree = RwLock::into_inner(Arc::try_unwrap(subtree).unwrap()).unwrap();
 
-        self.db.put_batch(
-            subtree
-                .into_iter()
-                .map(|(key, value)| (key.into(), H::serialize(value)))
-                .collect(),
-        )?;
+        // The roots of empty subtrees that are not persisted yet are skipped. Whether they are
+        // persisted is read at once, and only for them, since other values are always written
+        let (empty, filled): (Vec<_>, Vec<_>) = subtree
+            .into_iter()
+            .partition(|&(key, value)| value == self.cache[key.0]);
+        let empty_keys = empty
+            .iter()
+            .map(|&(key, _)| key.into())
+            .collect::<Vec<DBKey>>();
+        let persisted = self.db.get_batch(&empty_keys)?;
+
+        let mut batch = HashMap::with_capacity(filled.len() + empty.len());
+        for (key, value) in filled {
+            batch.insert(key.into(), H::serialize(value));
+        }
+        for ((key, value), persisted) in empty.into_iter().zip(persisted) {
+            if persisted.is_some() {
+                batch.insert(key.into(), H::serialize(value));
+            }
+        }
+        for (i, (&leaf, &node)) in raw_leaves.iter().zip(&leaves).enumerate() {
+            if node != leaf {
+                batch.insert(self.leaf_key(start + i), H::serialize(leaf));
+            }
+        }
+
+        self.db.put_batch(batch)?;
 
         // Update next_index value in db
         if end > self.next_index {
@@ -309,6 +605,7 @@ where
         key: Key,
         subtree: Arc<RwLock<HashMap<Key, H::Fr>>>,
         depth: usize,
+        domain: Option<H::Fr>,
     ) -> H::Fr {
         let left_child = Key(key.0 + 1, key.1 * 2);
         let right_child = Key(key.0 + 1, key.1 * 2 + 1);
@@ -318,11 +615,11 @@ where
         }
 
         let (left, right) = rayon::join(
-            || Self::batch_recalculate(left_child, Arc::clone(&subtree), depth),
-            || Self::batch_recalculate(right_child, Arc::clone(&subtree), depth),
+            || Self::batch_recalculate(left_child, Arc::clone(&subtree), depth, domain),
+            || Self::batch_recalculate(right_child, Arc::clone(&subtree), depth, domain),
         );
 
-        let result = H::hash(&[left, right]);
+        let result = hash_node::<H>(domain, left, right);
 
         subtree.write().unwrap().insert(key, result);
 
@@ -335,32 +632,118 @@ where
             return Err(PmtreeErrorKind::TreeError(TreeErrorKind::IndexOutOfBounds));
         }
 
-        let mut witness = Vec::with_capacity(self.depth);
+        let mut keys = Vec::with_capacity(self.depth);
 
         let mut i = index;
         let mut depth = self.depth;
         while depth != 0 {
             i ^= 1;
-            witness.push((
-                self.get_elem(Key(depth, i))?,
-                (1 - (i & 1)).try_into().unwrap(),
-            ));
+            keys.push(Key(depth, i));
             i >>= 1;
             depth -= 1;
         }
 
+        // Fetches all the siblings with a single db call
+        let values = self
+            .db
+            .get_batch(&keys.iter().map(|&key| key.into()).collect::<Vec<DBKey>>())?;
+
+        let witness = keys
+            .into_iter()
+            .zip(values)
+            .map(|(key, value)| {
+                (
+                    value.map_or(self.cache[key.0], |value| H::deserialize(value)),
+                    (1 - (key.1 & 1)).try_into().unwrap(),
+                )
+            })
+            .collect();
+
         Ok(MerkleProof(witness))
     }
 
+    /// Enables the value->index map used by `proof_by_leaf`, built from the current leaves.
+    ///
+    /// The map is then kept up to date on every insertion and deletion. It is kept in memory only.
+    /// Every index of a value set more than once is kept, so that overwriting one of them does not
+    /// remove the value from the map.
+    pub fn enable_leaf_index(&mut self) -> PmtreeResult<()> {
+        let mut leaf_index: HashMap<Value, BTreeSet<usize>> = HashMap::new();
+        for i in 0..self.next_index {
+            let node = self.get_leaf_node(i)?;
+            if node != H::default_leaf() {
+                leaf_index.entry(H::serialize(node)).or_default().insert(i);
+            }
+        }
+
+        self.leaf_index = Some(leaf_index);
+
+        Ok(())
+    }
+
+    /// Returns the index of the input leaf, if it is in the tree
+    ///
+    /// If the leaf was set at several indexes, the lowest one is returned.
+    /// Uses the value->index map when enabled, scans the leaf layer otherwise.
+    pub fn leaf_index(&self, leaf: &H::Fr) -> PmtreeResult<Option<usize>> {
+        let node = H::hash_leaf(*leaf);
+
+        // Empty leaves are never indexed
+        if node == H::default_leaf() {
+            return Ok(None);
+        }
+
+        if let Some(leaf_index) = &self.leaf_index {
+            return Ok(leaf_index
+                .get(&H::serialize(node))
+                .and_then(|indexes| indexes.first().copied()));
+        }
+
+        for i in 0..self.next_index {
+            if self.get_leaf_node(i)? == node {
+                return Ok(Some(i));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Computes a Merkle proof for the input leaf, returns `None` if the leaf is not in the tree
+    pub fn proof_by_leaf(&self, leaf: &H::Fr) -> PmtreeResult<Option<MerkleProof<H>>> {
+        match self.leaf_index(leaf)? {
+            Some(index) => Ok(Some(self.proof(index)?)),
+            None => Ok(None),
+        }
+    }
+
     /// Verifies a Merkle proof with respect to the input leaf and the tree root
     pub fn verify(&self, leaf: &H::Fr, witness: &MerkleProof<H>) -> bool {
-        let expected_root = witness.compute_root_from(leaf);
+        let expected_root =
+            witness.compute_root_from_with_domain(&H::hash_leaf(*leaf), self.domain);
 
         self.root() == expected_root
     }
 
     /// Returns the leaf by the key
     pub fn get(&self, key: usize) -> PmtreeResult<H::Fr> {
+        let node = self.get_leaf_node(key)?;
+
+        // The leaf is only stored when it differs from its leaf node, and it is stale if the leaf
+        // node was overwritten since
+        if H::hash_leaf(node) != node {
+            if let Some(leaf) = self.db.get(self.leaf_key(key))? {
+                let leaf = H::deserialize(leaf);
+                if H::hash_leaf(leaf) == node {
+                    return Ok(leaf);
+                }
+            }
+        }
+
+        Ok(node)
+    }
+
+    /// Returns the leaf node (i.e. `H::hash_leaf(leaf)`) by the key
+    pub fn get_leaf_node(&self, key: usize) -> PmtreeResult<H::Fr> {
         if key >= self.capacity() {
             return Err(PmtreeErrorKind::TreeError(TreeErrorKind::IndexOutOfBounds));
         }
@@ -378,6 +761,11 @@ where
         self.next_index
     }
 
+    /// Returns the index of the next insertion
+    pub fn next_index(&self) -> usize {
+        self.next_index
+    }
+
     /// Returns the capacity of the tree, i.e. the maximum number of leaves
     pub fn capacity(&self) -> usize {
         1 << self.depth
@@ -387,17 +775,29 @@ where
     pub fn depth(&self) -> usize {
         self.depth
     }
+
+    /// Returns the domain internal nodes are hashed with, if any
+    pub fn domain(&self) -> Option<H::Fr> {
+        self.domain
+    }
 }
 
 impl<H: Hasher> MerkleProof<H> {
-    /// Computes the Merkle root by iteratively hashing specified Merkle proof with specified leaf
+    /// Computes the Merkle root by iteratively hashing specified Merkle proof with specified leaf node
+    ///
+    /// The leaf node is `H::hash_leaf(leaf)`, see `MerkleTree::verify` to verify a raw leaf.
     pub fn compute_root_from(&self, leaf: &H::Fr) -> H::Fr {
+        self.compute_root_from_with_domain(leaf, None)
+    }
+
+    /// Computes the Merkle root for a tree whose internal nodes are hashed with the given domain
+    pub fn compute_root_from_with_domain(&self, leaf: &H::Fr, domain: Option<H::Fr>) -> H::Fr {
         let mut acc = *leaf;
         for w in self.0.iter() {
             if w.1 == 0 {
-                acc = H::hash(&[acc, w.0]);
+                acc = hash_node::<H>(domain, acc, w.0);
             } else {
-                acc = H::hash(&[w.0, acc]);
+                acc = hash_node::<H>(domain, w.0, acc);
             }
         }
 
@@ -427,3 +827,11 @@ impl<H: Hasher> MerkleProof<H> {
         self.0.len()
     }
 }
+
+// Hashes the children of an internal node, prepending the domain if any
+fn hash_node<H: Hasher>(domain: Option<H::Fr>, left: H::Fr, right: H::Fr) -> H::Fr {
+    match domain {
+        Some(domain) => H::hash(&[domain, left, right]),
+        None => H::hash(&[left, right]),
+    }
+}
@@ -1,10 +1,19 @@
 use hex_literal::hex;
+use std::cell::Cell;
 use std::collections::HashMap;
 use tiny_keccak::{Hasher as _, Keccak};
+use zk_kit_pmt::tree::MerkleProof;
 use zk_kit_pmt::*;
 
 struct MemoryDB(HashMap<DBKey, Value>);
+struct CountingMemoryDB {
+    db: HashMap<DBKey, Value>,
+    gets: Cell<usize>,
+    batch_gets: Cell<usize>,
+}
 struct MyKeccak(());
+struct MyLeafKeccak(());
+struct MyMisconfiguredKeccak(());
 
 #[derive(Default)]
 struct MemoryDBConfig;
@@ -38,6 +47,62 @@ impl Database for MemoryDB {
         Ok(())
     }
 
+    fn iter(&self) -> PmtreeResult<Box<dyn Iterator<Item = (DBKey, Value)> + '_>> {
+        Ok(Box::new(
+            self.0.iter().map(|(&key, value)| (key, value.clone())),
+        ))
+    }
+
+    fn snapshot(&self) -> PmtreeResult<Self> {
+        Ok(MemoryDB(self.0.clone()))
+    }
+
+    fn close(&mut self) -> PmtreeResult<()> {
+        Ok(())
+    }
+}
+
+impl Database for CountingMemoryDB {
+    type Config = MemoryDBConfig;
+
+    fn new(_db_config: MemoryDBConfig) -> PmtreeResult<Self> {
+        Ok(CountingMemoryDB {
+            db: HashMap::new(),
+            gets: Cell::new(0),
+            batch_gets: Cell::new(0),
+        })
+    }
+
+    fn load(_db_config: MemoryDBConfig) -> PmtreeResult<Self> {
+        Err(PmtreeErrorKind::DatabaseError(
+            DatabaseErrorKind::CannotLoadDatabase,
+        ))
+    }
+
+    fn get(&self, key: DBKey) -> PmtreeResult<Option<Value>> {
+        self.gets.set(self.gets.get() + 1);
+
+        Ok(self.db.get(&key).cloned())
+    }
+
+    fn get_batch(&self, keys: &[DBKey]) -> PmtreeResult<Vec<Option<Value>>> {
+        self.batch_gets.set(self.batch_gets.get() + 1);
+
+        Ok(keys.iter().map(|key| self.db.get(key).cloned()).collect())
+    }
+
+    fn put(&mut self, key: DBKey, value: Value) -> PmtreeResult<()> {
+        self.db.insert(key, value);
+
+        Ok(())
+    }
+
+    fn put_batch(&mut self, subtree: HashMap<DBKey, Value>) -> PmtreeResult<()> {
+        self.db.extend(subtree);
+
+        Ok(())
+    }
+
     fn close(&mut self) -> PmtreeResult<()> {
         Ok(())
     }
@@ -46,6 +111,8 @@ impl Database for MemoryDB {
 impl Hasher for MyKeccak {
     type Fr = [u8; 32];
 
+    const WIDTH: usize = 32;
+
     fn default_leaf() -> Self::Fr {
         [0; 32]
     }
@@ -69,6 +136,55 @@ impl Hasher for MyKeccak {
     }
 }
 
+impl Hasher for MyLeafKeccak {
+    type Fr = [u8; 32];
+
+    const WIDTH: usize = 32;
+
+    fn default_leaf() -> Self::Fr {
+        [0; 32]
+    }
+
+    fn serialize(value: Self::Fr) -> Value {
+        value.to_vec()
+    }
+
+    fn deserialize(value: Value) -> Self::Fr {
+        value.try_into().unwrap()
+    }
+
+    fn hash(input: &[Self::Fr]) -> Self::Fr {
+        MyKeccak::hash(input)
+    }
+
+    fn hash_leaf(leaf: Self::Fr) -> Self::Fr {
+        let mut output = [0; 32];
+        let mut hasher = Keccak::v256();
+        hasher.update(&[0]);
+        hasher.update(&leaf);
+        hasher.finalize(&mut output);
+        output
+    }
+}
+
+impl Hasher for MyMisconfiguredKeccak {
+    type Fr = [u8; 32];
+
+    const WIDTH: usize = 64;
+
+    fn serialize(value: Self::Fr) -> Value {
+        value.to_vec()
+    }
+
+    fn deserialize(value: Value) -> Self::Fr {
+        value.try_into().unwrap()
+    }
+
+    fn hash(input: &[Self::Fr]) -> Self::Fr {
+        MyKeccak::hash(input)
+    }
+}
+
 #[test]
 fn insert_delete() -> PmtreeResult<()> {
     let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(2, MemoryDBConfig)?;
@@ -104,8 +220,13 @@ fn insert_delete() -> PmtreeResult<()> {
         assert!(mt.verify(&leaf, &mt.proof(i)?));
     }
 
+    // An overwritten leaf is not returned anymore
+    mt.set(0, leaves[3])?;
+    assert_eq!(mt.get(0)?, leaves[3]);
+
     for i in (0..leaves.len()).rev() {
         mt.delete(i)?;
+        assert_eq!(mt.get(i)?, MyLeafKeccak::default_leaf());
     }
 
     assert_eq!(mt.root(), default_tree_root);
@@ -154,3 +275,496 @@ fn set_range() -> PmtreeResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn set_next_index() -> PmtreeResult<()> {
+    let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(2, MemoryDBConfig)?;
+
+    let leaves = [
+        hex!("0000000000000000000000000000000000000000000000000000000000000001"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000002"),
+    ];
+
+    mt.set_next_index(2)?;
+    assert_eq!(mt.next_index(), 2);
+
+    mt.update_next(leaves[0])?;
+    assert_eq!(mt.next_index(), 3);
+    assert_eq!(mt.get(2)?, leaves[0]);
+
+    let mut expected = MerkleTree::<MemoryDB, MyKeccak>::new(2, MemoryDBConfig)?;
+    expected.set(2, leaves[0])?;
+    assert_eq!(mt.root(), expected.root());
+
+    // The cursor cannot be moved back over existing leaves
+    assert!(mt.set_next_index(2).is_err());
+    mt.set_next_index(3)?;
+    assert!(mt.set_next_index(5).is_err());
+    mt.set_next_index(4)?;
+    assert!(mt.update_next(leaves[1]).is_err());
+
+    mt.delete(2)?;
+    mt.set_next_index(1)?;
+    mt.batch_insert(None, &leaves)?;
+    assert_eq!(mt.get(1)?, leaves[0]);
+    assert_eq!(mt.get(2)?, leaves[1]);
+    assert_eq!(mt.next_index(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn domain_separation() -> PmtreeResult<()> {
+    let leaves = [
+        hex!("0000000000000000000000000000000000000000000000000000000000000001"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000002"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000003"),
+    ];
+    let domain_a = hex!("00000000000000000000000000000000000000000000000000000000000000aa");
+    let domain_b = hex!("00000000000000000000000000000000000000000000000000000000000000bb");
+
+    let mut plain = MerkleTree::<MemoryDB, MyKeccak>::new(2, MemoryDBConfig)?;
+    let mut mt_a =
+        MerkleTree::<MemoryDB, MyKeccak>::new_with_domain(2, MemoryDBConfig, Some(domain_a))?;
+    let mut mt_b =
+        MerkleTree::<MemoryDB, MyKeccak>::new_with_domain(2, MemoryDBConfig, Some(domain_b))?;
+
+    assert_eq!(plain.domain(), None);
+    assert_eq!(mt_a.domain(), Some(domain_a));
+    assert_ne!(mt_a.root(), mt_b.root());
+
+    plain.batch_insert(None, &leaves)?;
+    mt_b.batch_insert(None, &leaves)?;
+    for &leaf in &leaves {
+        mt_a.update_next(leaf)?;
+    }
+
+    assert_ne!(mt_a.root(), mt_b.root());
+    assert_ne!(mt_a.root(), plain.root());
+    assert_ne!(mt_b.root(), plain.root());
+
+    let mut expected =
+        MerkleTree::<MemoryDB, MyKeccak>::new_with_domain(2, MemoryDBConfig, Some(domain_a))?;
+    expected.batch_insert(None, &leaves)?;
+    assert_eq!(mt_a.root(), expected.root());
+
+    for (i, leaf) in leaves.iter().enumerate() {
+        let proof = mt_a.proof(i)?;
+        assert!(mt_a.verify(leaf, &proof));
+        assert_eq!(
+            proof.compute_root_from_with_domain(leaf, Some(domain_a)),
+            mt_a.root()
+        );
+
+        // Proofs do not verify across domains
+        assert!(!mt_b.verify(leaf, &proof));
+        assert!(!plain.verify(leaf, &proof));
+        assert!(!mt_a.verify(leaf, &mt_b.proof(i)?));
+        assert!(!mt_a.verify(leaf, &plain.proof(i)?));
+    }
+
+    let fork = mt_a.fork()?;
+    assert_eq!(fork.domain(), Some(domain_a));
+    assert!(fork.verify(&leaves[0], &mt_a.proof(0)?));
+
+    Ok(())
+}
+
+#[test]
+fn append_with_frontier() -> PmtreeResult<()> {
+    let leaves: Vec<[u8; 32]> = (1..=8u8)
+        .map(|i| {
+            let mut leaf = [0; 32];
+            leaf[31] = i;
+            leaf
+        })
+        .collect();
+
+    let mut expected = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+
+    // Resumes from a tree filled with `update_next`
+    for &leaf in &leaves[..3] {
+        expected.update_next(leaf)?;
+        mt.update_next(leaf)?;
+    }
+
+    let mut frontier = mt.frontier()?;
+    assert_eq!(frontier.len(), 3);
+    assert!(mt
+        .append_with_frontier(&mut frontier[..2], leaves[3])
+        .is_err());
+
+    for &leaf in &leaves[3..] {
+        expected.update_next(leaf)?;
+        mt.append_with_frontier(&mut frontier, leaf)?;
+
+        assert_eq!(mt.root(), expected.root());
+        assert_eq!(mt.leaves_set(), expected.leaves_set());
+        assert_eq!(frontier, mt.frontier()?);
+    }
+
+    for (i, leaf) in leaves.iter().enumerate() {
+        assert_eq!(mt.get(i)?, *leaf);
+        assert!(mt.verify(leaf, &mt.proof(i)?));
+    }
+
+    assert!(mt.append_with_frontier(&mut frontier, leaves[0]).is_err());
+
+    // The frontier is hashed with the domain of the tree
+    let domain = hex!("00000000000000000000000000000000000000000000000000000000000000aa");
+    let mut expected =
+        MerkleTree::<MemoryDB, MyKeccak>::new_with_domain(3, MemoryDBConfig, Some(domain))?;
+    let mut mt =
+        MerkleTree::<MemoryDB, MyKeccak>::new_with_domain(3, MemoryDBConfig, Some(domain))?;
+    let mut frontier = mt.frontier()?;
+    for &leaf in &leaves[..5] {
+        expected.update_next(leaf)?;
+        mt.append_with_frontier(&mut frontier, leaf)?;
+        assert_eq!(mt.root(), expected.root());
+    }
+
+    // A stale frontier is rejected without modifying the tree
+    let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    mt.update_next(leaves[0])?;
+    let mut stale_frontier = mt.frontier()?;
+    mt.update_next(leaves[1])?;
+    let root = mt.root();
+
+    assert!(mt
+        .append_with_frontier(&mut stale_frontier, leaves[2])
+        .is_err());
+    assert_eq!(mt.root(), root);
+    assert_eq!(mt.leaves_set(), 2);
+
+    // Empty subtrees are not persisted, as with `update_next`
+    let mut expected = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    let mut frontier = mt.frontier()?;
+    for leaf in [
+        MyKeccak::default_leaf(),
+        leaves[0],
+        MyKeccak::default_leaf(),
+    ] {
+        expected.update_next(leaf)?;
+        mt.append_with_frontier(&mut frontier, leaf)?;
+
+        assert_eq!(mt.root(), expected.root());
+        assert_eq!(mt.db.0.len(), expected.db.0.len());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn leaf_hashing() -> PmtreeResult<()> {
+    let mut mt = MerkleTree::<MemoryDB, MyLeafKeccak>::new(2, MemoryDBConfig)?;
+    let default_tree_root = mt.root();
+
+    let leaves = [
+        hex!("0000000000000000000000000000000000000000000000000000000000000001"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000002"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000003"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000004"),
+    ];
+
+    for &leaf in &leaves {
+        mt.update_next(leaf)?;
+    }
+
+    let mut batch_mt = MerkleTree::<MemoryDB, MyLeafKeccak>::new(2, MemoryDBConfig)?;
+    batch_mt.batch_insert(None, &leaves)?;
+    assert_eq!(batch_mt.root(), mt.root());
+
+    for (i, &leaf) in leaves.iter().enumerate() {
+        assert_eq!(mt.get(i)?, leaf);
+        assert_eq!(batch_mt.get(i)?, leaf);
+        assert_eq!(mt.get_leaf_node(i)?, MyLeafKeccak::hash_leaf(leaf));
+        assert!(mt.verify(&leaf, &mt.proof(i)?));
+    }
+
+    // The leaves set with `append_with_frontier` are returned as well
+    let mut frontier_mt = MerkleTree::<MemoryDB, MyLeafKeccak>::new(2, MemoryDBConfig)?;
+    let mut frontier = frontier_mt.frontier()?;
+    frontier_mt.append_with_frontier(&mut frontier, leaves[0])?;
+    assert_eq!(frontier_mt.get(0)?, leaves[0]);
+
+    // An internal node presented as a leaf with a shortened proof does not verify.
+    let leaf_nodes = leaves.map(MyLeafKeccak::hash_leaf);
+    let internal_node = MyLeafKeccak::hash(&[leaf_nodes[0], leaf_nodes[1]]);
+    let sibling = MyLeafKeccak::hash(&[leaf_nodes[2], leaf_nodes[3]]);
+    let forged_proof = MerkleProof(vec![(sibling, 0)]);
+
+    assert_eq!(forged_proof.compute_root_from(&internal_node), mt.root());
+    assert!(!mt.verify(&internal_node, &forged_proof));
+
+    for i in (0..leaves.len()).rev() {
+        mt.delete(i)?;
+    }
+
+    assert_eq!(mt.root(), default_tree_root);
+
+    Ok(())
+}
+
+#[test]
+fn proof_by_leaf() -> PmtreeResult<()> {
+    let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    let mut indexed_mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+
+    let leaves = [
+        hex!("0000000000000000000000000000000000000000000000000000000000000001"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000002"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000003"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000004"),
+    ];
+    let missing_leaf = hex!("0000000000000000000000000000000000000000000000000000000000000005");
+
+    mt.update_next(leaves[0])?;
+    indexed_mt.update_next(leaves[0])?;
+    indexed_mt.enable_leaf_index()?;
+
+    mt.batch_insert(None, &leaves[1..3])?;
+    indexed_mt.batch_insert(None, &leaves[1..3])?;
+    mt.set(5, leaves[3])?;
+    indexed_mt.set(5, leaves[3])?;
+
+    for tree in [&mt, &indexed_mt] {
+        for (&leaf, index) in leaves.iter().zip([0, 1, 2, 5]) {
+            let proof = tree.proof_by_leaf(&leaf)?.unwrap();
+
+            assert_eq!(proof.leaf_index(), index);
+            assert!(tree.verify(&leaf, &proof));
+        }
+
+        assert!(tree.proof_by_leaf(&missing_leaf)?.is_none());
+        assert!(tree.proof_by_leaf(&MyKeccak::default_leaf())?.is_none());
+    }
+
+    // The map follows overwrites and deletions
+    indexed_mt.set(1, missing_leaf)?;
+    indexed_mt.delete(2)?;
+
+    assert!(indexed_mt.proof_by_leaf(&leaves[1])?.is_none());
+    assert!(indexed_mt.proof_by_leaf(&leaves[2])?.is_none());
+    assert_eq!(indexed_mt.leaf_index(&missing_leaf)?, Some(1));
+
+    Ok(())
+}
+
+#[test]
+fn proof_by_duplicate_leaf() -> PmtreeResult<()> {
+    let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    let mut indexed_mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    indexed_mt.enable_leaf_index()?;
+
+    let leaf = hex!("0000000000000000000000000000000000000000000000000000000000000001");
+    let other_leaf = hex!("0000000000000000000000000000000000000000000000000000000000000002");
+
+    for tree in [&mut mt, &mut indexed_mt] {
+        tree.set(1, leaf)?;
+        tree.set(4, leaf)?;
+        tree.set(6, leaf)?;
+    }
+
+    // The map built from the existing leaves agrees with the one kept up to date
+    let mut late_indexed_mt = mt.fork()?;
+    late_indexed_mt.enable_leaf_index()?;
+
+    for tree in [&mut mt, &mut indexed_mt, &mut late_indexed_mt] {
+        assert_eq!(tree.leaf_index(&leaf)?, Some(1));
+
+        // Overwriting one copy of the leaf keeps the others
+        tree.set(1, other_leaf)?;
+        assert_eq!(tree.leaf_index(&leaf)?, Some(4));
+        assert_eq!(tree.leaf_index(&other_leaf)?, Some(1));
+
+        tree.delete(4)?;
+        let proof = tree.proof_by_leaf(&leaf)?.unwrap();
+        assert_eq!(proof.leaf_index(), 6);
+        assert!(tree.verify(&leaf, &proof));
+
+        tree.delete(6)?;
+        assert!(tree.proof_by_leaf(&leaf)?.is_none());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn fork() -> PmtreeResult<()> {
+    let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(2, MemoryDBConfig)?;
+
+    let leaves = [
+        hex!("0000000000000000000000000000000000000000000000000000000000000001"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000002"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000003"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000004"),
+    ];
+
+    mt.update_next(leaves[0])?;
+    let root = mt.root();
+
+    let mut fork = mt.fork()?;
+    assert_eq!(fork.root(), root);
+    assert_eq!(fork.leaves_set(), 1);
+
+    // The fork diverges from the original tree
+    fork.batch_insert(None, &leaves[1..])?;
+    fork.delete(0)?;
+
+    assert_eq!(mt.root(), root);
+    assert_eq!(mt.leaves_set(), 1);
+    assert_eq!(mt.get(0)?, leaves[0]);
+    assert_eq!(mt.get(1)?, MyKeccak::default_leaf());
+    assert!(mt.verify(&leaves[0], &mt.proof(0)?));
+
+    // The original tree diverges from the fork
+    mt.update_next(leaves[3])?;
+
+    let mut expected = MerkleTree::<MemoryDB, MyKeccak>::new(2, MemoryDBConfig)?;
+    expected.batch_insert(
+        None,
+        &[MyKeccak::default_leaf(), leaves[1], leaves[2], leaves[3]],
+    )?;
+    assert_eq!(fork.root(), expected.root());
+    assert_eq!(fork.leaves_set(), 4);
+
+    // Databases without snapshots cannot be forked
+    let mt = MerkleTree::<CountingMemoryDB, MyKeccak>::new(2, MemoryDBConfig)?;
+    assert!(matches!(
+        mt.fork(),
+        Err(PmtreeErrorKind::DatabaseError(
+            DatabaseErrorKind::CannotLoadDatabase
+        ))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn dump() -> PmtreeResult<()> {
+    let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(2, MemoryDBConfig)?;
+
+    let leaves = [
+        hex!("0000000000000000000000000000000000000000000000000000000000000001"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000002"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000003"),
+    ];
+
+    mt.batch_insert(None, &leaves)?;
+
+    let dump = mt.dump()?;
+
+    assert_eq!(dump.len(), mt.db.0.len());
+    assert!(dump.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    for (key, value) in &dump {
+        assert_eq!(mt.db.get(*key)?.as_ref(), Some(value));
+    }
+
+    // The dump contains the metadata, the root and the leaves
+    let dump: HashMap<DBKey, Value> = dump.into_iter().collect();
+    assert_eq!(dump[&(u64::MAX - 1).to_be_bytes()], 2usize.to_be_bytes());
+    assert_eq!(dump[&u64::MAX.to_be_bytes()], 3usize.to_be_bytes());
+    assert_eq!(dump[&0u64.to_be_bytes()], mt.root());
+    for value in leaves {
+        assert!(dump.values().any(|v| *v == value));
+    }
+
+    // Databases without iteration cannot be dumped
+    let mt = MerkleTree::<CountingMemoryDB, MyKeccak>::new(2, MemoryDBConfig)?;
+    assert!(matches!(
+        mt.dump(),
+        Err(PmtreeErrorKind::DatabaseError(
+            DatabaseErrorKind::CustomError(_)
+        ))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn empty_subtrees_are_not_persisted() -> PmtreeResult<()> {
+    let mut mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+
+    // Only the depth and the next index are persisted
+    assert_eq!(mt.db.0.len(), 2);
+    let empty_root = (0..3).fold(MyKeccak::default_leaf(), |node, _| {
+        MyKeccak::hash(&[node, node])
+    });
+    assert_eq!(mt.root(), empty_root);
+    assert_eq!(mt.get(5)?, MyKeccak::default_leaf());
+    for i in 0..mt.capacity() {
+        assert!(mt.verify(&MyKeccak::default_leaf(), &mt.proof(i)?));
+    }
+
+    let leaves = [
+        hex!("0000000000000000000000000000000000000000000000000000000000000001"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000002"),
+    ];
+
+    // A leaf and its path are persisted, but not its empty siblings
+    mt.update_next(leaves[0])?;
+    assert_eq!(mt.db.0.len(), 2 + 4);
+
+    let mut batch_mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    batch_mt.batch_insert(None, &leaves[..1])?;
+    assert_eq!(batch_mt.db.0.len(), 2 + 4);
+    assert_eq!(batch_mt.root(), mt.root());
+
+    mt.update_next(leaves[1])?;
+    assert_eq!(mt.db.0.len(), 2 + 5);
+    assert!(mt.verify(&leaves[1], &mt.proof(1)?));
+
+    // Persisted nodes are overwritten when their subtree becomes empty
+    mt.delete(0)?;
+    mt.delete(1)?;
+    let empty_mt = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    assert_eq!(mt.root(), empty_mt.root());
+    for i in 0..mt.capacity() {
+        assert_eq!(mt.proof(i)?.0, empty_mt.proof(i)?.0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn hasher_width_mismatch() {
+    let result = MerkleTree::<MemoryDB, MyMisconfiguredKeccak>::new(2, MemoryDBConfig);
+
+    assert!(matches!(
+        result,
+        Err(PmtreeErrorKind::DatabaseError(
+            DatabaseErrorKind::CustomError(_)
+        ))
+    ));
+}
+
+#[test]
+fn batched_proof_reads() -> PmtreeResult<()> {
+    let mut mt = MerkleTree::<CountingMemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+    let mut expected = MerkleTree::<MemoryDB, MyKeccak>::new(3, MemoryDBConfig)?;
+
+    let leaves = [
+        hex!("0000000000000000000000000000000000000000000000000000000000000001"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000002"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000003"),
+    ];
+
+    mt.batch_insert(None, &leaves)?;
+    expected.batch_insert(None, &leaves)?;
+
+    for i in 0..mt.capacity() {
+        mt.db.gets.set(0);
+        mt.db.batch_gets.set(0);
+
+        let proof = mt.proof(i)?;
+
+        assert_eq!(mt.db.gets.get(), 0);
+        assert_eq!(mt.db.batch_gets.get(), 1);
+        assert_eq!(proof.0, expected.proof(i)?.0);
+        assert!(mt.verify(&mt.get(i)?, &proof));
+    }
+
+    Ok(())
+}
@@ -73,6 +73,8 @@ impl Database for MySled {
 impl Hasher for MyKeccak {
     type Fr = [u8; 32];
 
+    const WIDTH: usize = 32;
+
     fn default_leaf() -> Self::Fr {
         [0; 32]
     }
@@ -202,3 +204,47 @@ fn set_range() -> PmtreeResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn reload_after_delete() -> PmtreeResult<()> {
+    let mut mt = MerkleTree::<MySled, MyKeccak>::new(
+        2,
+        SledConfig {
+            path: String::from("abacabasac"),
+        },
+    )?;
+
+    let leaves = [
+        hex!("0000000000000000000000000000000000000000000000000000000000000001"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000002"),
+        hex!("0000000000000000000000000000000000000000000000000000000000000003"),
+    ];
+
+    mt.batch_insert(None, &leaves)?;
+
+    // The persisted nodes are overwritten with the roots of empty subtrees, by both the single
+    // and the batch updates
+    mt.delete(2)?;
+    mt.set_range(0, [MyKeccak::default_leaf(); 2])?;
+
+    let default_tree_root =
+        hex!("b4c11951957c6f8f642c4af61cd6b24640fec6dc7fc607ee8206a99e92410d30");
+    assert_eq!(mt.root(), default_tree_root);
+
+    mt.db.close()?;
+    drop(mt);
+
+    let mt = MerkleTree::<MySled, MyKeccak>::load(SledConfig {
+        path: String::from("abacabasac"),
+    })?;
+
+    assert_eq!(mt.root(), default_tree_root);
+    for i in 0..leaves.len() {
+        assert_eq!(mt.get(i)?, MyKeccak::default_leaf());
+        assert!(mt.verify(&MyKeccak::default_leaf(), &mt.proof(i)?));
+    }
+
+    fs::remove_dir_all("abacabasac").expect("Error removing db");
+
+    Ok(())
+}
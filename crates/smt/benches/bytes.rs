@@ -0,0 +1,62 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use zk_kit_smt::smt::{Key, Node, Value, SMT};
+
+fn hash_function(nodes: Vec<Node>) -> Node {
+    let strings: Vec<String> = nodes.iter().map(|node| node.to_string()).collect();
+    Node::Str(strings.join(","))
+}
+
+fn keys() -> Vec<[u8; 32]> {
+    (0..64u8)
+        .map(|i| {
+            let mut key = [i.wrapping_mul(151); 32];
+            key[31] = i;
+            key
+        })
+        .collect()
+}
+
+fn bench_bytes(c: &mut Criterion) {
+    let keys = keys();
+
+    c.bench_function("add string keys", |b| {
+        b.iter(|| {
+            let mut smt = SMT::new(hash_function, false);
+            for key in &keys {
+                smt.add(Key::Str(hex::encode(key)), Value::Str("ff".to_string()))
+                    .unwrap();
+            }
+            smt
+        })
+    });
+
+    c.bench_function("add binary keys", |b| {
+        b.iter(|| {
+            let mut smt = SMT::new(hash_function, false);
+            for key in &keys {
+                smt.add_bytes(key, &[0xff]).unwrap();
+            }
+            smt
+        })
+    });
+
+    let mut smt = SMT::new(hash_function, false);
+    for key in &keys {
+        smt.add_bytes(key, &[0xff]).unwrap();
+    }
+    let key = keys[42];
+
+    c.bench_function("proof with string key", |b| {
+        b.iter(|| {
+            smt.create_proof(black_box(Key::Str(hex::encode(key))))
+                .unwrap()
+        })
+    });
+
+    c.bench_function("proof with binary key", |b| {
+        b.iter(|| smt.create_proof_bytes(black_box(&key)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_bytes);
+criterion_main!(benches);
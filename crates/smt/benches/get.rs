@@ -0,0 +1,34 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use num_bigint::BigInt;
+use zk_kit_smt::smt::{Key, Node, Value, SMT};
+
+fn hash_function(nodes: Vec<Node>) -> Node {
+    let strings: Vec<String> = nodes.iter().map(|node| node.to_string()).collect();
+    Node::Str(strings.join(","))
+}
+
+fn populate(smt: &mut SMT) {
+    for i in 0..64 {
+        smt.add(Key::BigInt(BigInt::from(i)), Value::BigInt(BigInt::from(i)))
+            .unwrap();
+    }
+}
+
+fn bench_get(c: &mut Criterion) {
+    let key = Key::BigInt(BigInt::from(42));
+
+    let mut smt = SMT::new(hash_function, true);
+    populate(&mut smt);
+    c.bench_function("get without cache", |b| {
+        b.iter(|| smt.get(black_box(key.clone())))
+    });
+
+    let mut smt = SMT::builder(hash_function, true).cache(16).build();
+    populate(&mut smt);
+    c.bench_function("get with cache", |b| {
+        b.iter(|| smt.get(black_box(key.clone())))
+    });
+}
+
+criterion_group!(benches, bench_get);
+criterion_main!(benches);
@@ -0,0 +1,105 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A counting Bloom filter.
+///
+/// Every item increments `hashes` counters on insertion and decrements them on removal, so items
+/// can be removed without rebuilding the filter. A counter that reaches its maximum value is never
+/// decremented again, since its exact count is lost: the filter may then report more false
+/// positives, but it never reports a false negative.
+pub(crate) struct CountingBloomFilter {
+    counters: Vec<u8>,
+    hashes: usize,
+}
+
+impl CountingBloomFilter {
+    /// Creates an empty filter with `size` counters and `hashes` hash functions per item.
+    pub(crate) fn new(size: usize, hashes: usize) -> Self {
+        CountingBloomFilter {
+            counters: vec![0; size.max(1)],
+            hashes: hashes.max(1),
+        }
+    }
+
+    /// Adds `item` to the filter.
+    pub(crate) fn insert<T: Hash>(&mut self, item: &T) {
+        for i in self.indices(item) {
+            self.counters[i] = self.counters[i].saturating_add(1);
+        }
+    }
+
+    /// Removes `item` from the filter, it must have been inserted before.
+    pub(crate) fn remove<T: Hash>(&mut self, item: &T) {
+        for i in self.indices(item) {
+            if self.counters[i] != u8::MAX {
+                self.counters[i] = self.counters[i].saturating_sub(1);
+            }
+        }
+    }
+
+    /// Returns `false` if `item` is definitely not in the filter, `true` if it may be.
+    pub(crate) fn contains<T: Hash>(&self, item: &T) -> bool {
+        self.indices(item).all(|i| self.counters[i] > 0)
+    }
+
+    /// Removes all the items of the filter.
+    pub(crate) fn clear(&mut self) {
+        self.counters.fill(0);
+    }
+
+    // Returns the counters of `item`, derived from two hashes with double hashing.
+    fn indices<T: Hash>(&self, item: &T) -> impl Iterator<Item = usize> {
+        let hash = |seed: u64| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            item.hash(&mut hasher);
+            hasher.finish()
+        };
+        let (h1, h2) = (hash(0), hash(1));
+        let size = self.counters.len() as u64;
+
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % size) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut filter = CountingBloomFilter::new(1024, 4);
+        assert!(!filter.contains(&"a"));
+
+        filter.insert(&"a");
+        filter.insert(&"b");
+        assert!(filter.contains(&"a"));
+        assert!(filter.contains(&"b"));
+
+        filter.remove(&"a");
+        assert!(!filter.contains(&"a"));
+        assert!(filter.contains(&"b"));
+
+        filter.clear();
+        assert!(!filter.contains(&"b"));
+    }
+
+    #[test]
+    fn test_saturation() {
+        // With a single counter, every item shares it.
+        let mut filter = CountingBloomFilter::new(1, 1);
+        for i in 0..300 {
+            filter.insert(&i);
+        }
+
+        // The counter is saturated, so it is never decremented again.
+        for i in 0..299 {
+            filter.remove(&i);
+        }
+        assert!(filter.contains(&299));
+
+        let mut filter = CountingBloomFilter::new(0, 0);
+        filter.insert(&"a");
+        assert!(filter.contains(&"a"));
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A bounded least-recently-used cache.
+///
+/// The entries are kept in a doubly linked list ordered by use, stored in a vector and indexed
+/// by a map, so lookups, insertions and evictions take constant time. When the cache is full,
+/// the slot of the least recently used entry is reused for the new entry.
+pub(crate) struct LruCache<K, V> {
+    capacity: usize,
+    map: HashMap<K, usize>,
+    entries: Vec<Entry<K, V>>,
+    // The most recently used entry.
+    head: Option<usize>,
+    // The least recently used entry.
+    tail: Option<usize>,
+}
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    /// Creates an empty cache holding at most `capacity` entries.
+    pub(crate) fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            map: HashMap::with_capacity(capacity),
+            entries: Vec::with_capacity(capacity),
+            head: None,
+            tail: None,
+        }
+    }
+
+    /// Returns the value cached for `key`, marking it as the most recently used.
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        let index = *self.map.get(key)?;
+        self.move_to_front(index);
+
+        Some(&self.entries[index].value)
+    }
+
+    /// Caches `value` for `key`, evicting the least recently used entry if the cache is full.
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Some(&index) = self.map.get(&key) {
+            self.entries[index].value = value;
+            self.move_to_front(index);
+            return;
+        }
+
+        let index = if self.entries.len() < self.capacity {
+            self.entries.push(Entry {
+                key: key.clone(),
+                value,
+                prev: None,
+                next: None,
+            });
+
+            self.entries.len() - 1
+        } else {
+            let index = self
+                .tail
+                .expect("a full cache has a least recently used entry");
+            self.detach(index);
+            self.map.remove(&self.entries[index].key);
+
+            let entry = &mut self.entries[index];
+            entry.key = key.clone();
+            entry.value = value;
+
+            index
+        };
+
+        self.map.insert(key, index);
+        self.push_front(index);
+    }
+
+    /// Removes all the cached entries.
+    pub(crate) fn clear(&mut self) {
+        self.map.clear();
+        self.entries.clear();
+        self.head = None;
+        self.tail = None;
+    }
+
+    /// Returns the number of cached entries.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    // Moves the entry at `index` to the front of the list.
+    fn move_to_front(&mut self, index: usize) {
+        if self.head != Some(index) {
+            self.detach(index);
+            self.push_front(index);
+        }
+    }
+
+    // Removes the entry at `index` from the list, its slot is kept.
+    fn detach(&mut self, index: usize) {
+        let (prev, next) = (self.entries[index].prev, self.entries[index].next);
+
+        match prev {
+            Some(prev) => self.entries[prev].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => self.entries[next].prev = prev,
+            None => self.tail = prev,
+        }
+
+        self.entries[index].prev = None;
+        self.entries[index].next = None;
+    }
+
+    // Inserts the detached entry at `index` at the front of the list.
+    fn push_front(&mut self, index: usize) {
+        self.entries[index].next = self.head;
+        if let Some(head) = self.head {
+            self.entries[head].prev = Some(index);
+        }
+
+        self.head = Some(index);
+        if self.tail.is_none() {
+            self.tail = Some(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_and_insert() {
+        let mut cache = LruCache::new(2);
+        assert_eq!(cache.get(&"a"), None);
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"b"), Some(&2));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+
+        // "a" becomes the most recently used entry, so "b" is evicted.
+        cache.get(&"a");
+        cache.insert("c", 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"c"), Some(&3));
+    }
+
+    #[test]
+    fn test_reinsert_and_repeated_eviction() {
+        let mut cache = LruCache::new(3);
+        for i in 0..3 {
+            cache.insert(i, i);
+        }
+
+        // Updating an entry marks it as the most recently used.
+        cache.insert(0, 10);
+        cache.insert(3, 3);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&0), Some(&10));
+
+        // The entries are evicted in order of use.
+        for i in 4..10 {
+            cache.insert(i, i);
+            assert_eq!(cache.len(), 3);
+        }
+        assert_eq!(cache.get(&7), Some(&7));
+        assert_eq!(cache.get(&8), Some(&8));
+        assert_eq!(cache.get(&9), Some(&9));
+        assert_eq!(cache.get(&6), None);
+
+        cache.get(&7);
+        cache.insert(10, 10);
+        assert_eq!(cache.get(&8), None);
+        assert_eq!(cache.get(&7), Some(&7));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.get(&"a"), None);
+
+        let mut cache = LruCache::new(0);
+        cache.insert("a", 1);
+        assert_eq!(cache.get(&"a"), None);
+    }
+}
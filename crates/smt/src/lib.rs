@@ -1,2 +1,4 @@
+mod bloom;
+mod cache;
 pub mod smt;
 mod utils;
@@ -1,9 +1,16 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
 
-use num_bigint::BigInt;
+use num_bigint::{BigInt, Sign};
 
+use crate::bloom::CountingBloomFilter;
+use crate::cache::LruCache;
 use crate::utils::{
-    get_first_common_elements, get_index_of_last_non_zero_element, is_hexadecimal, key_to_path,
+    bytes_to_path, get_first_common_elements, get_index_of_last_non_zero_element, is_hexadecimal,
+    key_to_path,
 };
 
 use std::fmt;
@@ -14,6 +21,9 @@ pub enum SMTError {
     KeyDoesNotExist(String),
     InvalidParameterType(String, String),
     InvalidSiblingIndex,
+    InvalidJson(String),
+    CorruptNode(String),
+    HashCollision(String),
 }
 
 impl fmt::Display for SMTError {
@@ -25,6 +35,9 @@ impl fmt::Display for SMTError {
                 write!(f, "Parameter {} must be a {}", p, t)
             },
             SMTError::InvalidSiblingIndex => write!(f, "Invalid sibling index"),
+            SMTError::InvalidJson(s) => write!(f, "Invalid SMT JSON: {}", s),
+            SMTError::CorruptNode(s) => write!(f, "Node {} is corrupted", s),
+            SMTError::HashCollision(s) => write!(f, "Hash collision on node {}", s),
         }
     }
 }
@@ -73,653 +86,3634 @@ pub type Siblings = Vec<Node>;
 
 pub type HashFunction = fn(ChildNodes) -> Node;
 
+/// The length of the paths of the tree, i.e. the maximum number of siblings of a proof.
+const PATH_LENGTH: usize = 256;
+
+/// The JSON representation of an SMT, see `SMT::to_json`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SMTJson {
+    big_numbers: bool,
+    zero_node: String,
+    entry_mark: String,
+    root: String,
+    nodes: Vec<(String, Vec<String>)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    values: Option<Vec<(String, String)>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct EntryResponse {
     pub entry: Vec<Node>,
     pub matching_entry: Option<Vec<Node>>,
     pub siblings: Siblings,
 }
 
-#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MerkleProof {
-    entry_response: EntryResponse,
-    root: Node,
-    membership: bool,
-}
-
-#[allow(dead_code)]
-pub struct SMT {
-    hash: HashFunction,
-    big_numbers: bool,
-    zero_node: Node,
-    entry_mark: Node,
-    nodes: HashMap<Node, Vec<Node>>,
-    root: Node,
+    pub entry_response: EntryResponse,
+    pub root: Node,
+    pub membership: bool,
+    pub zero_siblings: Vec<usize>,
 }
 
-impl SMT {
-    /// Initializes a new instance of the Sparse Merkle Tree (SMT).
+impl MerkleProof {
+    /// Removes the zero siblings from the proof to reduce its size.
+    ///
+    /// Paths are 256 bits long, so the siblings of entries whose keys share a long prefix are
+    /// mostly zero nodes. The positions of the removed siblings are kept in the proof, which
+    /// can be restored with `decompress`. `verify_proof` accepts both compressed and
+    /// uncompressed proofs.
     ///
     /// # Arguments
     ///
-    /// * `hash` - The hash function used to hash the child nodes.
-    /// * `big_numbers` - A flag indicating whether the SMT supports big numbers or not.
+    /// * `zero_node` - The zero node of the tree the proof was created from.
     ///
     /// # Returns
     ///
-    /// A new instance of the SMT.
-    pub fn new(hash: HashFunction, big_numbers: bool) -> Self {
-        let zero_node;
-        let entry_mark;
+    /// The compressed proof.
+    pub fn compress(mut self, zero_node: &Node) -> MerkleProof {
+        let siblings = self.decompressed_siblings(zero_node);
 
-        if big_numbers {
-            zero_node = Node::BigInt(BigInt::from(0));
-            entry_mark = Node::BigInt(BigInt::from(1));
-        } else {
-            zero_node = Node::Str("0".to_string());
-            entry_mark = Node::Str("1".to_string());
-        }
+        self.zero_siblings = Vec::new();
+        self.entry_response.siblings = Vec::with_capacity(siblings.len());
 
-        SMT {
-            hash,
-            big_numbers,
-            zero_node: zero_node.clone(),
-            entry_mark,
-            nodes: HashMap::new(),
-            root: zero_node,
+        for (i, sibling) in siblings.into_iter().enumerate() {
+            if sibling == *zero_node {
+                self.zero_siblings.push(i);
+            } else {
+                self.entry_response.siblings.push(sibling);
+            }
         }
+
+        self
     }
 
-    /// Retrieves the value associated with the given key from the SMT.
+    /// Restores the zero siblings removed by `compress`.
     ///
     /// # Arguments
     ///
-    /// * `key` - The key to retrieve the value for.
+    /// * `zero_node` - The zero node of the tree the proof was created from.
     ///
     /// # Returns
     ///
-    /// An `Option` containing the value associated with the key, or `None` if the key does not exist.
-    pub fn get(&self, key: Key) -> Option<Value> {
-        let key = key.to_string().parse::<Node>().unwrap();
-
-        let EntryResponse { entry, .. } = self.retrieve_entry(key);
+    /// The decompressed proof.
+    pub fn decompress(mut self, zero_node: &Node) -> MerkleProof {
+        self.entry_response.siblings = self.decompressed_siblings(zero_node);
+        self.zero_siblings = Vec::new();
 
-        entry.get(1).cloned()
+        self
     }
 
-    /// Adds a new key-value pair to the SMT.
-    ///
-    /// It retrieves a matching entry or a zero node with a top-down approach and then it updates
-    /// all the hashes of the nodes in the path of the new entry with a bottom up approach.
+    /// Returns the size in bytes of the proof under a canonical encoding.
     ///
-    /// # Arguments
-    ///
-    /// * `key` - The key to add.
-    /// * `value` - The value associated with the key.
+    /// Each node (of the entry, of the matching entry, and each sibling) is encoded as its string
+    /// representation, i.e. the decimal or hexadecimal string used by the JavaScript library, and
+    /// each position of a zero sibling removed by `compress` takes a byte (paths are 256 bits long).
+    /// The root and the membership flag are not counted, as they are known to the verifier.
     ///
     /// # Returns
     ///
-    /// An `Result` indicating whether the operation was successful or not.
-    pub fn add(&mut self, key: Key, value: Value) -> Result<(), SMTError> {
-        let key = key.to_string().parse::<Node>().unwrap();
-        let value = value.to_string().parse::<Node>().unwrap();
-
+    /// The size of the proof in bytes.
+    pub fn size_bytes(&self) -> usize {
         let EntryResponse {
             entry,
             matching_entry,
-            mut siblings,
-        } = self.retrieve_entry(key.clone());
+            siblings,
+        } = &self.entry_response;
 
-        if entry.get(1).is_some() {
-            return Err(SMTError::KeyAlreadyExist(key.to_string()));
-        }
+        entry
+            .iter()
+            .chain(matching_entry.iter().flatten())
+            .chain(siblings)
+            .map(|node| node.to_string().len())
+            .sum::<usize>()
+            + self.zero_siblings.len()
+    }
 
-        let path = key_to_path(&key.to_string());
-        // If there is a matching entry, its node is saved in the `node` variable, otherwise the
-        // `zero_node` is saved. This node is used below as the first node (starting from the
-        // bottom of the tree) to obtain the new nodes up to the root.
-        let node = if let Some(ref matching_entry) = matching_entry {
-            (self.hash)(matching_entry.clone())
-        } else {
-            self.zero_node.clone()
+    /// Checks whether the proof is minimal, i.e. it carries no zero sibling that `compress`
+    /// would remove.
+    ///
+    /// # Arguments
+    ///
+    /// * `zero_node` - The zero node of the tree the proof was created from.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the proof is minimal or not.
+    pub fn is_minimal(&self, zero_node: &Node) -> bool {
+        !self.entry_response.siblings.contains(zero_node)
+    }
+
+    /// Returns the direction of each sibling of the proof, starting from the root.
+    ///
+    /// The bits are the prefix of the path of the node the root is reconstructed from, i.e. the
+    /// key of the matching entry if there is one, or the key of the entry otherwise. A bit is 1
+    /// if the node is the right child, in which case its sibling is the left child. With the
+    /// (decompressed) siblings, the bits are enough to reconstruct the root without deriving
+    /// the path from the key.
+    ///
+    /// # Returns
+    ///
+    /// The path bits, one for each sibling, or an empty vector if the proof is malformed, i.e.
+    /// the key is missing or cannot be converted to a path, or there are more than 256 siblings.
+    pub fn path_bits(&self) -> Vec<usize> {
+        let key = match &self.entry_response.matching_entry {
+            Some(matching_entry) => matching_entry.first(),
+            None => self.entry_response.entry.first(),
         };
+        let length = self.entry_response.siblings.len() + self.zero_siblings.len();
 
-        // If there are siblings, the old nodes are deleted and will be re-created below with new hashes.
-        if !siblings.is_empty() {
-            self.delete_old_nodes(node.clone(), &path, &siblings)
+        match key.map(checked_key_path) {
+            Some(Ok(mut path)) if length <= PATH_LENGTH => {
+                path.truncate(length);
+                path
+            },
+            _ => Vec::new(),
         }
+    }
 
-        // If there is a matching entry, further N zero siblings are added in the `siblings` vector,
-        // followed by the matching node itself. N is the number of the first matching bits of the paths.
-        // This is helpful in the non-membership proof verification as explained in the function below.
-        if let Some(matching_entry) = matching_entry {
-            let matching_path = key_to_path(&matching_entry[0].to_string());
-            let mut i = siblings.len();
+    /// Returns the siblings of the proof with the zero siblings removed by `compress` restored.
+    fn decompressed_siblings(&self, zero_node: &Node) -> Siblings {
+        let length = self.entry_response.siblings.len() + self.zero_siblings.len();
+        let mut siblings = Vec::with_capacity(length);
+        let mut non_zero_siblings = self.entry_response.siblings.iter();
+        let mut zero_siblings = self.zero_siblings.iter().peekable();
 
-            while matching_path[i] == path[i] {
-                siblings.push(self.zero_node.clone());
-                i += 1;
+        for i in 0..length {
+            if zero_siblings.next_if_eq(&&i).is_some() {
+                siblings.push(zero_node.clone());
+            } else if let Some(sibling) = non_zero_siblings.next() {
+                siblings.push(sibling.clone());
             }
-
-            siblings.push(node.clone());
         }
 
-        // Adds the new entry and re-creates the nodes of the path with the new hashes with a bottom
-        // up approach. The `add_new_nodes` function returns the new root of the tree.
-        let new_node = (self.hash)(vec![key.clone(), value.clone(), self.entry_mark.clone()]);
+        siblings
+    }
+}
 
-        self.nodes
-            .insert(new_node.clone(), vec![key, value, self.entry_mark.clone()]);
-        self.root = self
-            .add_new_nodes(new_node, &path, &siblings, None)
-            .unwrap();
+/// An operation of a batch applied with `SMT::apply`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SMTOp {
+    Add(Key, Value),
+    Update(Key, Value),
+    Delete(Key),
+}
 
-        Ok(())
-    }
+/// A staged change of a batch: the key, its path, and its new value, or `None` if it is deleted.
+type Change = (Key, Vec<usize>, Option<Value>);
 
-    /// Updates the value associated with the given key in the SMT.
+/// A non-membership proof for a single key of a `BatchNonMembershipProof`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonMembershipEntry {
+    key: Key,
+    matching_entry: Option<Vec<Node>>,
+    siblings: Vec<usize>,
+}
+
+/// A proof of the non-membership of several keys against the same root.
+///
+/// The siblings of all the keys are stored once in a shared vector, and each key refers to its
+/// siblings by their indexes, so the siblings of overlapping paths are not repeated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchNonMembershipProof {
+    root: Node,
+    siblings: Siblings,
+    entries: Vec<NonMembershipEntry>,
+}
+
+/// A builder of an `SMT` with a custom configuration, created with `SMT::builder`.
+#[derive(Debug, Clone)]
+pub struct SMTBuilder {
+    hash: HashFunction,
+    big_numbers: bool,
+    zero_node: Option<Node>,
+    entry_mark: Option<Node>,
+    cache_capacity: Option<usize>,
+    separate_values: bool,
+    bloom_filter: Option<(usize, usize)>,
+}
+
+impl SMTBuilder {
+    /// Sets a custom zero node.
     ///
-    /// Also in this case, all the hashes of the nodes in the path of the updated entry are updated
-    /// with a bottom up approach.
+    /// The zero node is the value of the empty subtrees and the root of the empty tree, so it must
+    /// be different from the hash of any node. The default zero node is `0`.
     ///
     /// # Arguments
     ///
-    /// * `key` - The key to update the value for.
-    /// * `value` - The new value associated with the key.
+    /// * `zero_node` - The node used to represent the empty subtrees.
     ///
     /// # Returns
     ///
-    /// An `Result` indicating whether the operation was successful or not.
-    pub fn update(&mut self, key: Key, value: Value) -> Result<(), SMTError> {
-        let key = key.to_string().parse::<Node>().unwrap();
-        let value = value.to_string().parse::<Node>().unwrap();
-
-        let EntryResponse {
-            entry, siblings, ..
-        } = self.retrieve_entry(key.clone());
-
-        if entry.get(1).is_none() {
-            return Err(SMTError::KeyDoesNotExist(key.to_string()));
-        }
-
-        let path = key_to_path(&key.to_string());
-
-        // Deletes the old nodes and re-creates them with the new hashes.
-        let old_node = (self.hash)(entry.clone());
-        self.nodes.remove(&old_node);
-        self.delete_old_nodes(old_node.clone(), &path, &siblings);
-
-        let new_node = (self.hash)(vec![key.clone(), value.clone(), self.entry_mark.clone()]);
-        self.nodes
-            .insert(new_node.clone(), vec![key, value, self.entry_mark.clone()]);
-        self.root = self
-            .add_new_nodes(new_node, &path, &siblings, None)
-            .unwrap();
+    /// The builder.
+    pub fn zero_node(mut self, zero_node: Node) -> Self {
+        self.zero_node = Some(zero_node);
 
-        Ok(())
+        self
     }
 
-    /// Deletes the key-value pair associated with the given key from the SMT.
+    /// Sets a custom entry mark.
     ///
-    /// Also in this case, all the hashes of the nodes in the path of the deleted entry are updated
-    /// with a bottom up approach.
+    /// The entry mark is the third element hashed in every leaf, i.e. `hash([key, value, entry_mark])`.
+    /// Trees with different entry marks hash identical entries differently, so their proofs do not
+    /// verify against each other.
     ///
     /// # Arguments
     ///
-    /// * `key` - The key to delete.
+    /// * `entry_mark` - The node used to mark the leaves of the tree.
     ///
     /// # Returns
     ///
-    /// An `Result` indicating whether the operation was successful or not.
-    pub fn delete(&mut self, key: Key) -> Result<(), SMTError> {
-        let key = key.to_string().parse::<Node>().unwrap();
-
-        let EntryResponse {
-            entry,
-            mut siblings,
-            ..
-        } = self.retrieve_entry(key.clone());
-
-        if entry.get(1).is_none() {
-            return Err(SMTError::KeyDoesNotExist(key.to_string()));
-        }
-
-        let path = key_to_path(&key.to_string());
-
-        let node = (self.hash)(entry.clone());
-        self.nodes.remove(&node);
-
-        self.root = self.zero_node.clone();
-
-        // If there are siblings, the old nodes are deleted and will be re-created below with new hashes.
-        if !siblings.is_empty() {
-            self.delete_old_nodes(node.clone(), &path, &siblings);
+    /// The builder.
+    pub fn entry_mark(mut self, entry_mark: Node) -> Self {
+        self.entry_mark = Some(entry_mark);
 
-            // If the last sibling is not a leaf node, it adds all the nodes of the path starting from
-            // a zero node, otherwise it removes the last non-zero sibling from the `siblings` vector
-            // and it starts from it by skipping the last zero nodes.
-            if !self.is_leaf(&siblings.last().cloned().unwrap()) {
-                self.root = self
-                    .add_new_nodes(self.zero_node.clone(), &path, &siblings, None)
-                    .unwrap();
-            } else {
-                let first_sibling = siblings.pop().unwrap();
-                let i = get_index_of_last_non_zero_element(
-                    siblings
-                        .iter()
-                        .map(|s| s.to_string())
-                        .collect::<Vec<String>>()
-                        .iter()
-                        .map(|s| s.as_str())
-                        .collect::<Vec<&str>>(),
-                );
-
-                self.root = self.add_new_nodes(first_sibling, &path, &siblings, Some(i))?;
-            }
-        }
-
-        Ok(())
+        self
     }
 
-    /// Creates a proof to prove the membership or the non-membership of a tree entry.
+    /// Enables a cache for the values returned by `get`.
+    ///
+    /// The cache keeps the `cache_capacity` most recently retrieved entries and is flushed
+    /// entirely on any mutation of the tree, so it never returns a stale value.
     ///
     /// # Arguments
     ///
-    /// * `key` - The key to create the proof for.
+    /// * `cache_capacity` - The maximum number of entries kept in the cache.
     ///
     /// # Returns
     ///
-    /// A `MerkleProof` containing the proof information.
-    pub fn create_proof(&self, key: Key) -> MerkleProof {
-        let key = key.to_string().parse::<Node>().unwrap();
-
-        let EntryResponse {
-            entry,
-            matching_entry,
-            siblings,
-        } = self.retrieve_entry(key);
+    /// The builder.
+    pub fn cache(mut self, cache_capacity: usize) -> Self {
+        self.cache_capacity = Some(cache_capacity);
 
-        // If the key exists, the function returns a proof with the entry itself, otherwise it returns
-        // a non-membership proof with the matching entry.
-        MerkleProof {
-            entry_response: EntryResponse {
-                entry: entry.clone(),
-                matching_entry,
-                siblings,
-            },
-            root: self.root.clone(),
-            membership: entry.get(1).is_some(),
-        }
+        self
     }
 
-    /// Verifies a membership or a non-membership proof for a given key in the SMT.
-    ///
-    /// # Arguments
+    /// Stores the values separately from the nodes.
     ///
-    /// * `merkle_proof` - The Merkle proof to verify.
+    /// The leaves of the tree only hold a digest of the values, i.e. each leaf is
+    /// `hash([key, hash([value]), entry_mark])`, while the values are kept in a separate map.
+    /// This keeps the tree operations lightweight when the values are large. Proofs contain the
+    /// digest of the value instead of the value itself.
     ///
     /// # Returns
     ///
-    /// A boolean indicating whether the proof is valid or not.
-    pub fn verify_proof(&self, merkle_proof: MerkleProof) -> bool {
-        // If there is no matching entry, it simply obtains the root hash by using the siblings and the
-        // path of the key.
-        if merkle_proof.entry_response.matching_entry.is_none() {
-            let path = key_to_path(&merkle_proof.entry_response.entry[0].to_string());
-            // If there is not an entry value, the proof is a non-membership proof. In this case, since there
-            // is not a matching entry, the node is set to a zero node. If there is an entry value, the proof
-            // is a membership proof and the node is set to the hash of the entry.
-            let node = if merkle_proof.entry_response.entry.get(1).is_some() {
-                (self.hash)(merkle_proof.entry_response.entry)
-            } else {
-                self.zero_node.clone()
-            };
-            let root = self.calculate_root(node, &path, &merkle_proof.entry_response.siblings);
-
-            // If the obtained root is equal to the proof root, then the proof is valid.
-            return root == merkle_proof.root;
-        }
-
-        // If there is a matching entry, the proof is definitely a non-membership proof. In this case, it checks
-        // if the matching node belongs to the tree, and then it checks if the number of the first matching bits
-        // of the keys is greater than or equal to the number of the siblings.
-        if let Some(matching_entry) = &merkle_proof.entry_response.matching_entry {
-            let matching_path = key_to_path(&matching_entry[0].to_string());
-            let node = (self.hash)(matching_entry.to_vec());
-            let root =
-                self.calculate_root(node, &matching_path, &merkle_proof.entry_response.siblings);
-
-            if merkle_proof.membership == (root == merkle_proof.root) {
-                let path = key_to_path(&merkle_proof.entry_response.entry[0].to_string());
-                // Returns the first common bits of the two keys: the non-member key and the matching key.
-                let first_matching_bits = get_first_common_elements(&path, &matching_path);
-
-                // If the non-member key was a key of a tree entry, the depth of the matching node should be
-                // greater than the number of the fisrt matching bits. Otherwise, the depth of the node can be
-                // defined by the number of its siblings.
-                return merkle_proof.entry_response.siblings.len() <= first_matching_bits.len();
-            }
-        }
+    /// The builder.
+    pub fn separate_values(mut self) -> Self {
+        self.separate_values = true;
 
-        false
+        self
     }
 
-    /// Retrieves the entry associated with the given key from the SMT.
-    ///
-    /// If the key passed as parameter exists in the SMT, the function returns the entry itself, otherwise
-    /// it returns the entry with only the key. When there is another matching entry in the same path, it
-    /// returns the matching entry as well.
+    /// Enables a Bloom filter over the keys.
     ///
-    /// In any case, the function returns the siblings of the path.
+    /// The filter is updated by the functions that add or delete entries, and `contains_key`
+    /// returns `false` without traversing the tree for the keys that are definitely not in it.
+    /// It is a counting filter, so deleted keys are removed from it without a rebuild. Its
+    /// counters saturate at 255, after which they are never decremented: the filter never
+    /// reports a false negative, but its false positive rate can only be restored by creating a
+    /// new tree with a larger filter.
     ///
     /// # Arguments
     ///
-    /// * `key` - The key to retrieve the entry for.
+    /// * `bloom_size` - The number of counters of the filter.
+    /// * `bloom_hashes` - The number of counters updated for each key.
     ///
     /// # Returns
     ///
-    /// An `EntryResponse` struct containing the entry, the matching entry (if any), and the siblings of the leaf node.
-    fn retrieve_entry(&self, key: Key) -> EntryResponse {
-        let path = key_to_path(&key.to_string());
-        let mut siblings: Siblings = Vec::new();
-        let mut node = self.root.clone();
-
-        let mut i = 0;
+    /// The builder.
+    pub fn bloom_filter(mut self, bloom_size: usize, bloom_hashes: usize) -> Self {
+        self.bloom_filter = Some((bloom_size, bloom_hashes));
 
-        // Starting from the root, it traverses the tree until it reaches a leaf node, a zero node,
-        // or a matching entry.
-        while node != self.zero_node {
-            let child_nodes = self.nodes.get(&node).unwrap_or(&Vec::new()).clone();
-            let direction = path[i];
+        self
+    }
 
-            // If the third element of the child nodes is not None, it means that the node is an entry of the tree.
-            if child_nodes.get(2).is_some() {
-                if child_nodes[0] == key {
-                    // An entry is found with the same key, and it returns it with the siblings.
-                    return EntryResponse {
-                        entry: child_nodes,
-                        matching_entry: None,
-                        siblings,
-                    };
-                }
+    /// Builds the SMT with the configuration of the builder.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the SMT.
+    pub fn build(self) -> SMT {
+        let mut smt = SMT::new(self.hash, self.big_numbers);
 
-                // An entry was found with a different key, but the key of this particular entry matches the first 'i'
-                // bits of the key passed as parameter. It can be useful in several functions.
-                return EntryResponse {
-                    entry: vec![key.clone()],
-                    matching_entry: Some(child_nodes),
-                    siblings,
-                };
-            }
+        if let Some(zero_node) = self.zero_node {
+            smt.zero_node = zero_node.clone();
+            smt.root = zero_node;
+        }
+        if let Some(entry_mark) = self.entry_mark {
+            smt.entry_mark = entry_mark;
+        }
+        if let Some(cache_capacity) = self.cache_capacity {
+            smt.cache = Some(Mutex::new(LruCache::new(cache_capacity)));
+        }
+        if self.separate_values {
+            smt.values = Some(HashMap::new());
+        }
+        if let Some((bloom_size, bloom_hashes)) = self.bloom_filter {
+            smt.bloom_filter = Some(CountingBloomFilter::new(bloom_size, bloom_hashes));
+        }
 
-            // When it goes down into the tree and follows the path, in every step a node is chosen between left
-            // and right child nodes, and the opposite node is saved in the `siblings` vector.
-            node = child_nodes[direction].clone();
-            siblings.push(child_nodes[1 - direction].clone());
+        smt
+    }
+}
 
-            i += 1;
+#[allow(dead_code)]
+pub struct SMT {
+    hash: HashFunction,
+    big_numbers: bool,
+    zero_node: Node,
+    entry_mark: Node,
+    nodes: HashMap<Node, Vec<Node>>,
+    root: Node,
+    cache: Option<Mutex<LruCache<Key, Value>>>,
+    values: Option<HashMap<Key, Value>>,
+    bloom_filter: Option<CountingBloomFilter>,
+}
+
+impl SMT {
+    /// Initializes a new instance of the Sparse Merkle Tree (SMT).
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash function used to hash the child nodes.
+    /// * `big_numbers` - A flag indicating whether the SMT supports big numbers or not.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the SMT.
+    pub fn new(hash: HashFunction, big_numbers: bool) -> Self {
+        let zero_node;
+        let entry_mark;
+
+        if big_numbers {
+            zero_node = Node::BigInt(BigInt::from(0));
+            entry_mark = Node::BigInt(BigInt::from(1));
+        } else {
+            zero_node = Node::Str("0".to_string());
+            entry_mark = Node::Str("1".to_string());
         }
 
-        // The path led to a zero node.
-        EntryResponse {
-            entry: vec![key],
-            matching_entry: None,
-            siblings,
+        SMT {
+            hash,
+            big_numbers,
+            zero_node: zero_node.clone(),
+            entry_mark,
+            nodes: HashMap::new(),
+            root: zero_node,
+            cache: None,
+            values: None,
+            bloom_filter: None,
         }
     }
 
-    /// Calculates the root of the tree by using the given node, the path, and the siblings.
+    /// Initializes a new instance of the SMT with a custom entry mark.
     ///
-    /// It calculates with a bottom up approach by starting from the node and going up to the root.
+    /// It is a shorthand for `SMT::builder(hash, big_numbers).entry_mark(entry_mark).build()`,
+    /// see `SMTBuilder::entry_mark`.
     ///
     /// # Arguments
     ///
-    /// * `node` - The node to start the calculation from.
-    /// * `path` - The path of the key.
-    /// * `siblings` - The siblings of the path.
+    /// * `hash` - The hash function used to hash the child nodes.
+    /// * `big_numbers` - A flag indicating whether the SMT supports big numbers or not.
+    /// * `entry_mark` - The node used to mark the leaves of the tree.
     ///
     /// # Returns
     ///
-    /// The root of the tree.
-    fn calculate_root(&self, mut node: Node, path: &[usize], siblings: &Siblings) -> Node {
-        for i in (0..siblings.len()).rev() {
-            let child_nodes: ChildNodes = if path[i] != 0 {
-                vec![siblings[i].clone(), node.clone()]
-            } else {
-                vec![node.clone(), siblings[i].clone()]
-            };
+    /// A new instance of the SMT.
+    pub fn new_with_mark(hash: HashFunction, big_numbers: bool, entry_mark: Node) -> Self {
+        SMT::builder(hash, big_numbers)
+            .entry_mark(entry_mark)
+            .build()
+    }
 
-            node = (self.hash)(child_nodes);
-        }
+    /// Initializes a new instance of the SMT with a custom zero node.
+    ///
+    /// It is a shorthand for `SMT::builder(hash, big_numbers).zero_node(zero_node).build()`,
+    /// see `SMTBuilder::zero_node`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash function used to hash the child nodes.
+    /// * `big_numbers` - A flag indicating whether the SMT supports big numbers or not.
+    /// * `zero_node` - The node used to represent the empty subtrees.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the SMT.
+    pub fn new_with_zero(hash: HashFunction, big_numbers: bool, zero_node: Node) -> Self {
+        SMT::builder(hash, big_numbers).zero_node(zero_node).build()
+    }
 
-        node
+    /// Initializes a new instance of the SMT with a cache for the values returned by `get`.
+    ///
+    /// It is a shorthand for `SMT::builder(hash, big_numbers).cache(cache_capacity).build()`,
+    /// see `SMTBuilder::cache`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash function used to hash the child nodes.
+    /// * `big_numbers` - A flag indicating whether the SMT supports big numbers or not.
+    /// * `cache_capacity` - The maximum number of entries kept in the cache.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the SMT.
+    pub fn new_with_cache(hash: HashFunction, big_numbers: bool, cache_capacity: usize) -> Self {
+        SMT::builder(hash, big_numbers)
+            .cache(cache_capacity)
+            .build()
     }
 
-    /// Adds new nodes to the tree with the new hashes.
+    /// Initializes a new instance of the SMT that stores the values separately from the nodes.
     ///
-    /// It starts with a bottom up approach until it reaches the root of the tree.
+    /// It is a shorthand for `SMT::builder(hash, big_numbers).separate_values().build()`, see
+    /// `SMTBuilder::separate_values`.
     ///
     /// # Arguments
     ///
-    /// * `node` - The node to start the calculation from.
-    /// * `path` - The path of the key.
-    /// * `siblings` - The siblings of the path.
-    /// * `i` - The index of the sibling to start from.
+    /// * `hash` - The hash function used to hash the child nodes.
+    /// * `big_numbers` - A flag indicating whether the SMT supports big numbers or not.
     ///
     /// # Returns
     ///
-    /// The new root of the tree.
-    fn add_new_nodes(
-        &mut self,
-        mut node: Node,
-        path: &[usize],
-        siblings: &Siblings,
-        i: Option<isize>,
-    ) -> Result<Node, SMTError> {
-        let mut starting_index = if let Some(i) = i {
-            i
-        } else {
-            siblings.len() as isize - 1
-        };
+    /// A new instance of the SMT.
+    pub fn new_with_separate_values(hash: HashFunction, big_numbers: bool) -> Self {
+        SMT::builder(hash, big_numbers).separate_values().build()
+    }
 
-        while starting_index > 0 {
-            if siblings.get(starting_index as usize).is_none() {
-                return Err(SMTError::InvalidSiblingIndex);
-            }
+    /// Initializes a new instance of the SMT with a Bloom filter over its keys.
+    ///
+    /// It is a shorthand for
+    /// `SMT::builder(hash, big_numbers).bloom_filter(bloom_size, bloom_hashes).build()`, see
+    /// `SMTBuilder::bloom_filter`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash function used to hash the child nodes.
+    /// * `big_numbers` - A flag indicating whether the SMT supports big numbers or not.
+    /// * `bloom_size` - The number of counters of the filter.
+    /// * `bloom_hashes` - The number of counters updated for each key.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the SMT.
+    pub fn new_with_bloom_filter(
+        hash: HashFunction,
+        big_numbers: bool,
+        bloom_size: usize,
+        bloom_hashes: usize,
+    ) -> Self {
+        SMT::builder(hash, big_numbers)
+            .bloom_filter(bloom_size, bloom_hashes)
+            .build()
+    }
 
-            let child_nodes: ChildNodes = if path.get(starting_index as usize).is_some() {
-                vec![siblings[starting_index as usize].clone(), node.clone()]
-            } else {
-                vec![node.clone(), siblings[starting_index as usize].clone()]
-            };
+    /// Creates a builder of an SMT with a custom configuration.
+    ///
+    /// The options of the builder can be combined, and the options that are not set keep the
+    /// values of `new`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash function used to hash the child nodes.
+    /// * `big_numbers` - A flag indicating whether the SMT supports big numbers or not.
+    ///
+    /// # Returns
+    ///
+    /// A new `SMTBuilder`.
+    pub fn builder(hash: HashFunction, big_numbers: bool) -> SMTBuilder {
+        SMTBuilder {
+            hash,
+            big_numbers,
+            zero_node: None,
+            entry_mark: None,
+            cache_capacity: None,
+            separate_values: false,
+            bloom_filter: None,
+        }
+    }
 
-            node = (self.hash)(child_nodes.clone());
+    /// Estimates how many entries a tree can hold before two of its nodes are likely to collide,
+    /// for a hash function whose outputs have the given number of bits.
+    ///
+    /// By the birthday bound, a collision among `2^(hash_bits / 2)` nodes is likely. A tree with
+    /// random keys holds less than four nodes per entry, so the estimate is `2^(hash_bits / 2 - 2)`
+    /// entries. Adding an entry whose nodes collide with existing ones fails with
+    /// `SMTError::HashCollision`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash_bits` - The number of bits of the outputs of the hash function.
+    ///
+    /// # Returns
+    ///
+    /// The estimated number of entries, at least 1.
+    pub fn capacity_estimate(hash_bits: usize) -> BigInt {
+        BigInt::from(1) << (hash_bits / 2).saturating_sub(2)
+    }
 
-            self.nodes.insert(node.clone(), child_nodes);
+    /// Computes the root of the SMT containing the given entries, without keeping the tree.
+    ///
+    /// The entries are added in order to a new tree, so the root is the same as the one of a
+    /// tree built with `add`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash function used to hash the child nodes.
+    /// * `big_numbers` - A flag indicating whether the SMT supports big numbers or not.
+    /// * `entries` - The key/value pairs of the tree.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the root of the tree, or an error if a key is repeated.
+    pub fn root_of(
+        hash: HashFunction,
+        big_numbers: bool,
+        entries: &[(Key, Value)],
+    ) -> Result<Node, SMTError> {
+        let mut smt = SMT::new(hash, big_numbers);
 
-            starting_index -= 1;
+        for (key, value) in entries {
+            smt.add(key.clone(), value.clone())?;
         }
 
-        Ok(node)
+        Ok(smt.root)
     }
 
-    /// Deletes the old nodes of the tree.
+    /// Serializes the SMT to the JSON format of the JavaScript `@zk-kit/smt` tree.
     ///
-    /// It starts with a bottom up approach until it reaches the root of the tree.
+    /// The layout is the following, where nodes are decimal strings in big numbers mode and
+    /// hexadecimal strings otherwise, and `nodes` holds the entries of the nodes map (i.e. what
+    /// `[...smt.nodes]` returns in JavaScript) sorted by node:
     ///
-    /// # Arguments
+    /// ```json
+    /// {
+    ///     "bigNumbers": true,
+    ///     "zeroNode": "0",
+    ///     "entryMark": "1",
+    ///     "root": "<node>",
+    ///     "nodes": [["<node>", ["<child node>", "<child node>"]], ["<node>", ["<key>", "<value>", "1"]]]
+    /// }
+    /// ```
     ///
-    /// * `node` - The node to start the calculation from.
-    /// * `path` - The path of the key.
-    /// * `siblings` - The siblings of the path.
-    fn delete_old_nodes(&mut self, mut node: Node, path: &[usize], siblings: &Siblings) {
-        for i in (0..siblings.len()).rev() {
-            let child_nodes: ChildNodes = if path.get(i).is_some() {
-                vec![siblings[i].clone(), node.clone()]
-            } else {
-                vec![node.clone(), siblings[i].clone()]
-            };
-
-            node = (self.hash)(child_nodes);
+    /// If the values are stored separately, they are serialized as an additional `values` list
+    /// of `[key, value]` pairs. The hash function and the cache are not serialized.
+    ///
+    /// # Returns
+    ///
+    /// The JSON string.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        let to_strings =
+            |nodes: &[Node]| -> Vec<String> { nodes.iter().map(|node| node.to_string()).collect() };
 
-            self.nodes.remove(&node);
+        let mut values: Option<Vec<(String, String)>> = self.values.as_ref().map(|values| {
+            values
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        });
+        if let Some(values) = &mut values {
+            values.sort();
         }
+
+        let smt_json = SMTJson {
+            big_numbers: self.big_numbers,
+            zero_node: self.zero_node.to_string(),
+            entry_mark: self.entry_mark.to_string(),
+            root: self.root.to_string(),
+            nodes: self
+                .iter_nodes()
+                .map(|(node, child_nodes)| (node.to_string(), to_strings(&child_nodes)))
+                .collect(),
+            values,
+        };
+
+        serde_json::to_string(&smt_json).unwrap()
     }
 
-    /// Checks if the given node is a leaf node or not.
+    /// Deserializes an SMT from the JSON format of the JavaScript `@zk-kit/smt` tree, see `to_json`.
+    ///
+    /// JSON strings do not tell `Str` nodes from `BigInt` nodes, so the tree is rebuilt with the
+    /// given hash function from the entries found in the nodes map, the keys and values being
+    /// parsed as in `add`. The rebuilt root must match the serialized root, which also checks that
+    /// the hash function is the one of the serialized tree.
     ///
     /// # Arguments
     ///
-    /// * `node` - The node to check.
+    /// * `json` - The JSON string.
+    /// * `hash` - The hash function of the serialized tree.
     ///
     /// # Returns
     ///
-    /// A boolean indicating whether the node is a leaf node or not.
-    fn is_leaf(&self, node: &Node) -> bool {
-        if let Some(child_nodes) = self.nodes.get(node) {
-            child_nodes.get(2).is_some()
-        } else {
-            false
+    /// A `Result` containing the SMT, or an error if the JSON is not a valid SMT.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str, hash: HashFunction) -> Result<Self, SMTError> {
+        let smt_json: SMTJson =
+            serde_json::from_str(json).map_err(|error| SMTError::InvalidJson(error.to_string()))?;
+
+        // The zero node and the entry mark are numbers in big numbers mode.
+        let big_numbers = smt_json.big_numbers;
+        let to_node = |s: &str| -> Node {
+            match s.parse::<BigInt>() {
+                Ok(number) if big_numbers => Node::BigInt(number),
+                _ => Node::Str(s.to_string()),
+            }
+        };
+        let parse_node = |s: &str| -> Result<Node, SMTError> {
+            s.parse::<Node>()
+                .map_err(|error| SMTError::InvalidJson(error.to_string()))
+        };
+
+        let mut smt = SMT::builder(hash, big_numbers)
+            .zero_node(to_node(&smt_json.zero_node))
+            .entry_mark(to_node(&smt_json.entry_mark))
+            .build();
+
+        let values: Option<HashMap<String, String>> =
+            smt_json.values.map(|values| values.into_iter().collect());
+        if values.is_some() {
+            smt.values = Some(HashMap::new());
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        for (_, child_nodes) in &smt_json.nodes {
+            if child_nodes.len() != 3 || child_nodes[2] != smt_json.entry_mark {
+                continue;
+            }
 
-    fn hash_function(nodes: Vec<Node>) -> Node {
-        let strings: Vec<String> = nodes.iter().map(|node| node.to_string()).collect();
-        Node::Str(strings.join(","))
-    }
+            let value = match &values {
+                Some(values) => values.get(&child_nodes[0]).ok_or_else(|| {
+                    SMTError::InvalidJson(format!("missing value of key {}", child_nodes[0]))
+                })?,
+                None => &child_nodes[1],
+            };
 
-    #[test]
-    fn test_new() {
-        let smt = SMT::new(hash_function, false);
-        assert!(!smt.big_numbers);
-        assert_eq!(smt.zero_node, Node::Str("0".to_string()));
-        assert_eq!(smt.entry_mark, Node::Str("1".to_string()));
-        assert_eq!(smt.nodes, HashMap::new());
-        assert_eq!(smt.root, Node::Str("0".to_string()));
+            smt.add(parse_node(&child_nodes[0])?, parse_node(value)?)?;
+        }
 
-        let smt = SMT::new(hash_function, true);
-        assert!(smt.big_numbers);
-        assert_eq!(smt.zero_node, Node::BigInt(BigInt::from(0)));
-        assert_eq!(smt.entry_mark, Node::BigInt(BigInt::from(1)));
-        assert_eq!(smt.nodes, HashMap::new());
-        assert_eq!(smt.root, Node::BigInt(BigInt::from(0)));
+        if smt.root.to_string() != smt_json.root {
+            return Err(SMTError::InvalidJson(
+                "the root does not match the nodes".to_string(),
+            ));
+        }
+
+        Ok(smt)
     }
 
-    #[test]
-    fn test_get() {
+    /// Retrieves the value associated with the given key from the SMT.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to retrieve the value for.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the value associated with the key, or `None` if the key does not exist.
+    pub fn get(&self, key: Key) -> Option<Value> {
+        let key = self.normalize_key(key).ok()?;
+        let path = key_path(&key);
+
+        self.get_with_path(key, &path)
+    }
+
+    /// Checks whether the given key exists in the SMT, without cloning its value.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to check.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the key exists or not.
+    pub fn contains_key(&self, key: &Key) -> bool {
+        let Ok(key) = self.normalize_key(key.clone()) else {
+            return false;
+        };
+
+        if let Some(bloom_filter) = &self.bloom_filter {
+            if !bloom_filter.contains(&key) {
+                return false;
+            }
+        }
+
+        if let Some(values) = &self.values {
+            return values.contains_key(&key);
+        }
+
+        let path = key_path(&key);
+        let mut node = &self.root;
+        let mut i = 0;
+
+        // Same traversal as `retrieve_entry`, but it only borrows the nodes.
+        while *node != self.zero_node {
+            let child_nodes = match self.nodes.get(node) {
+                Some(child_nodes) => child_nodes,
+                None => return false,
+            };
+
+            if child_nodes.get(2).is_some() {
+                return child_nodes[0] == key;
+            }
+
+            node = &child_nodes[path[i]];
+            i += 1;
+        }
+
+        false
+    }
+
+    /// Retrieves the value associated with the given key and path from the SMT.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The normalized key to retrieve the value for.
+    /// * `path` - The path of the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the value associated with the key, or `None` if the key does not exist.
+    fn get_with_path(&self, key: Key, path: &[usize]) -> Option<Value> {
+        if let Some(values) = &self.values {
+            return values.get(&key).cloned();
+        }
+
+        if let Some(cache) = &self.cache {
+            if let Some(value) = lock_cache(cache).get(&key) {
+                return Some(value.clone());
+            }
+        }
+
+        let EntryResponse { entry, .. } = self.retrieve_entry_with_path(key.clone(), path);
+        let value = entry.get(1).cloned();
+
+        if let (Some(cache), Some(value)) = (&self.cache, &value) {
+            lock_cache(cache).insert(key, value.clone());
+        }
+
+        value
+    }
+
+    /// Adds a new key-value pair to the SMT.
+    ///
+    /// It retrieves a matching entry or a zero node with a top-down approach and then it updates
+    /// all the hashes of the nodes in the path of the new entry with a bottom up approach.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to add.
+    /// * `value` - The value associated with the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    pub fn add(&mut self, key: Key, value: Value) -> Result<(), SMTError> {
+        let key = self.normalize_key(key)?;
+        let value = value.to_string().parse::<Node>()?;
+        let path = key_path(&key);
+
+        self.add_with_path(key, value, &path)
+    }
+
+    /// Adds a new key-value pair to the SMT by using the given path of the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The normalized key to add.
+    /// * `value` - The value associated with the key.
+    /// * `path` - The path of the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    fn add_with_path(&mut self, key: Key, value: Value, path: &[usize]) -> Result<(), SMTError> {
+        let EntryResponse {
+            entry,
+            matching_entry,
+            siblings,
+        } = self.retrieve_entry_with_path(key.clone(), path);
+
+        if entry.get(1).is_some() {
+            return Err(SMTError::KeyAlreadyExist(key.to_string()));
+        }
+
+        self.add_entry(key, value, path, matching_entry, siblings)
+    }
+
+    /// Adds a new entry to the SMT, once the key is known not to exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The normalized key to add.
+    /// * `value` - The value associated with the key.
+    /// * `path` - The path of the key.
+    /// * `matching_entry` - The matching entry returned by `retrieve_entry` for the key.
+    /// * `siblings` - The siblings returned by `retrieve_entry` for the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    fn add_entry(
+        &mut self,
+        key: Key,
+        value: Value,
+        path: &[usize],
+        matching_entry: Option<Vec<Node>>,
+        mut siblings: Siblings,
+    ) -> Result<(), SMTError> {
+        self.flush_cache();
+
+        // If there is a matching entry, its node is saved in the `node` variable, otherwise the
+        // `zero_node` is saved. This node is used below as the first node (starting from the
+        // bottom of the tree) to obtain the new nodes up to the root.
+        let node = if let Some(ref matching_entry) = matching_entry {
+            (self.hash)(matching_entry.clone())
+        } else {
+            self.zero_node.clone()
+        };
+
+        // If there are siblings, the old nodes are deleted and will be re-created below with new hashes.
+        if !siblings.is_empty() {
+            self.delete_old_nodes(node.clone(), path, &siblings)
+        }
+
+        // If there is a matching entry, further N zero siblings are added in the `siblings` vector,
+        // followed by the matching node itself. N is the number of the first matching bits of the paths.
+        // This is helpful in the non-membership proof verification as explained in the function below.
+        if let Some(matching_entry) = matching_entry {
+            let matching_path = key_path(&matching_entry[0]);
+            let mut i = siblings.len();
+
+            while matching_path[i] == path[i] {
+                siblings.push(self.zero_node.clone());
+                i += 1;
+            }
+
+            siblings.push(node.clone());
+        }
+
+        if let Some(bloom_filter) = &mut self.bloom_filter {
+            bloom_filter.insert(&key);
+        }
+
+        // Adds the new entry and re-creates the nodes of the path with the new hashes with a bottom
+        // up approach. The `add_new_nodes` function returns the new root of the tree.
+        let new_node = self.insert_entry(key, value)?;
+
+        self.root = self.add_new_nodes(new_node, path, &siblings, None)?;
+
+        Ok(())
+    }
+
+    /// Adds a new key-value pair of raw bytes to the SMT.
+    ///
+    /// The key is converted to its canonical node from its bytes and its path is derived from its
+    /// bits, without going through its hexadecimal encoding. Keys are compared by their numeric
+    /// value, so leading zero bytes are ignored. The value is the hexadecimal encoding of the bytes,
+    /// parsed as `add` does, so the entry is the same as the one added by `add` with the
+    /// hexadecimal encodings of the key and the value.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to add, a non-empty big-endian byte array of at most 32 bytes.
+    /// * `value` - The value associated with the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    pub fn add_bytes(&mut self, key: &[u8], value: &[u8]) -> Result<(), SMTError> {
+        let (key, path) = self.bytes_key(key)?;
+        let value = hex::encode(value).parse::<Node>()?;
+
+        self.add_with_path(key, value, &path)
+    }
+
+    /// Retrieves the raw bytes value associated with the given raw bytes key from the SMT.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to retrieve the value for, a non-empty big-endian byte array of at most 32 bytes.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the value associated with the key, or `None` if the key does not exist
+    /// or its value is not a hexadecimal encoding of bytes. A hexadecimal encoding made of decimal
+    /// digits only is parsed as a number when the value is added, so its leading zero bytes are
+    /// not returned.
+    pub fn get_bytes(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let (key, path) = self.bytes_key(key).ok()?;
+
+        let value = match self.get_with_path(key, &path)? {
+            Node::Str(value) => value,
+            Node::BigInt(number) if number.sign() != Sign::Minus => {
+                let digits = number.to_string();
+                if digits.len() % 2 == 0 {
+                    digits
+                } else {
+                    format!("0{}", digits)
+                }
+            },
+            Node::BigInt(_) => return None,
+        };
+
+        hex::decode(value).ok()
+    }
+
+    /// Converts a raw bytes key to its normalized node and its path.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to convert, a non-empty big-endian byte array of at most 32 bytes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the key node and its path.
+    fn bytes_key(&self, key: &[u8]) -> Result<(Key, Vec<usize>), SMTError> {
+        if key.is_empty() || key.len() > 32 {
+            return Err(SMTError::InvalidParameterType(
+                hex::encode(key),
+                "non-empty byte array of at most 32 bytes".to_string(),
+            ));
+        }
+
+        let node = self.canonical_key(BigInt::from_bytes_be(Sign::Plus, key));
+
+        Ok((node, bytes_to_path(key)))
+    }
+
+    /// Updates the value associated with the given key in the SMT.
+    ///
+    /// Also in this case, all the hashes of the nodes in the path of the updated entry are updated
+    /// with a bottom up approach.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to update the value for.
+    /// * `value` - The new value associated with the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    pub fn update(&mut self, key: Key, value: Value) -> Result<(), SMTError> {
+        let key = self.normalize_key(key)?;
+        let value = value.to_string().parse::<Node>()?;
+        let path = key_path(&key);
+
+        self.update_with_path(key, value, &path)
+    }
+
+    /// Updates the raw bytes value associated with the given raw bytes key in the SMT.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to update the value for, a non-empty big-endian byte array of at most 32 bytes.
+    /// * `value` - The new value associated with the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    pub fn update_bytes(&mut self, key: &[u8], value: &[u8]) -> Result<(), SMTError> {
+        let (key, path) = self.bytes_key(key)?;
+        let value = hex::encode(value).parse::<Node>()?;
+
+        self.update_with_path(key, value, &path)
+    }
+
+    /// Updates the value associated with the given key in the SMT by using the given path of the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The normalized key to update the value for.
+    /// * `value` - The new value associated with the key.
+    /// * `path` - The path of the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    fn update_with_path(&mut self, key: Key, value: Value, path: &[usize]) -> Result<(), SMTError> {
+        let EntryResponse {
+            entry, siblings, ..
+        } = self.retrieve_entry_with_path(key.clone(), path);
+
+        if entry.get(1).is_none() {
+            return Err(SMTError::KeyDoesNotExist(key.to_string()));
+        }
+
+        self.update_entry(key, value, path, entry, siblings)
+    }
+
+    /// Adds the given key-value pair to the SMT if the key does not exist, or updates its value
+    /// otherwise.
+    ///
+    /// Unlike calling `add` and then `update`, the tree is traversed only once.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to add or update.
+    /// * `value` - The value associated with the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    pub fn upsert(&mut self, key: Key, value: Value) -> Result<(), SMTError> {
+        let key = self.normalize_key(key)?;
+        let value = value.to_string().parse::<Node>()?;
+        let path = key_path(&key);
+
+        let EntryResponse {
+            entry,
+            matching_entry,
+            siblings,
+        } = self.retrieve_entry_with_path(key.clone(), &path);
+
+        if entry.get(1).is_some() {
+            self.update_entry(key, value, &path, entry, siblings)
+        } else {
+            self.add_entry(key, value, &path, matching_entry, siblings)
+        }
+    }
+
+    /// Replaces the value of an existing entry of the SMT.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The normalized key to update.
+    /// * `value` - The new value associated with the key.
+    /// * `path` - The path of the key.
+    /// * `entry` - The current entry returned by `retrieve_entry` for the key.
+    /// * `siblings` - The siblings returned by `retrieve_entry` for the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    fn update_entry(
+        &mut self,
+        key: Key,
+        value: Value,
+        path: &[usize],
+        entry: Vec<Node>,
+        siblings: Siblings,
+    ) -> Result<(), SMTError> {
+        self.flush_cache();
+
+        // Deletes the old nodes and re-creates them with the new hashes.
+        let old_node = (self.hash)(entry);
+        self.nodes.remove(&old_node);
+        self.delete_old_nodes(old_node, path, &siblings);
+
+        let new_node = self.insert_entry(key, value)?;
+
+        self.root = self.add_new_nodes(new_node, path, &siblings, None)?;
+
+        Ok(())
+    }
+
+    /// Deletes the key-value pair associated with the given key from the SMT.
+    ///
+    /// Also in this case, all the hashes of the nodes in the path of the deleted entry are updated
+    /// with a bottom up approach.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to delete.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    pub fn delete(&mut self, key: Key) -> Result<(), SMTError> {
+        let key = self.normalize_key(key)?;
+        let path = key_path(&key);
+
+        self.delete_with_path(key, &path)
+    }
+
+    /// Deletes the key-value pair associated with the given raw bytes key from the SMT.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to delete, a non-empty big-endian byte array of at most 32 bytes.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    pub fn delete_bytes(&mut self, key: &[u8]) -> Result<(), SMTError> {
+        let (key, path) = self.bytes_key(key)?;
+
+        self.delete_with_path(key, &path)
+    }
+
+    /// Deletes the key-value pair associated with the given key from the SMT by using the given
+    /// path of the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The normalized key to delete.
+    /// * `path` - The path of the key.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    fn delete_with_path(&mut self, key: Key, path: &[usize]) -> Result<(), SMTError> {
+        let EntryResponse {
+            entry,
+            mut siblings,
+            ..
+        } = self.retrieve_entry_with_path(key.clone(), path);
+
+        if entry.get(1).is_none() {
+            return Err(SMTError::KeyDoesNotExist(key.to_string()));
+        }
+
+        self.flush_cache();
+
+        let node = (self.hash)(entry.clone());
+        self.nodes.remove(&node);
+        if let Some(values) = &mut self.values {
+            values.remove(&key);
+        }
+        if let Some(bloom_filter) = &mut self.bloom_filter {
+            bloom_filter.remove(&key);
+        }
+
+        self.root = self.zero_node.clone();
+
+        // If there are siblings, the old nodes are deleted and will be re-created below with new hashes.
+        if !siblings.is_empty() {
+            self.delete_old_nodes(node.clone(), path, &siblings);
+
+            // If the last sibling is not a leaf node, it adds all the nodes of the path starting from
+            // a zero node, otherwise it removes the last non-zero sibling from the `siblings` vector
+            // and it starts from it by skipping the last zero nodes.
+            if !self.is_leaf(&siblings.last().cloned().unwrap()) {
+                self.root = self.add_new_nodes(self.zero_node.clone(), path, &siblings, None)?;
+            } else {
+                let first_sibling = siblings.pop().unwrap();
+                let i = get_index_of_last_non_zero_element(
+                    siblings
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect::<Vec<String>>()
+                        .iter()
+                        .map(|s| s.as_str())
+                        .collect::<Vec<&str>>(),
+                    &self.zero_node.to_string(),
+                );
+
+                self.root = self.add_new_nodes(first_sibling, path, &siblings, Some(i))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies a batch of operations to the SMT.
+    ///
+    /// The operations are validated in order as if they were applied one by one, e.g. a key can
+    /// be added and then updated in the same batch. Then all the changes are applied with a single
+    /// bottom up pass, so the nodes shared by the paths of several changed entries are computed
+    /// only once. The resulting tree is the same as the one obtained by applying the operations
+    /// one by one.
+    ///
+    /// The batch is atomic: if an operation is invalid, an error is returned and the SMT is not
+    /// modified.
+    ///
+    /// # Arguments
+    ///
+    /// * `ops` - The operations to apply.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    pub fn apply(&mut self, ops: &[SMTOp]) -> Result<(), SMTError> {
+        // The staged changes, with whether the key existed before the batch.
+        let mut staged: HashMap<Key, (bool, Option<Value>)> = HashMap::new();
+
+        for op in ops {
+            let (key, value) = match op {
+                SMTOp::Add(key, value) | SMTOp::Update(key, value) => (key, Some(value)),
+                SMTOp::Delete(key) => (key, None),
+            };
+            let key = self.normalize_key(key.clone())?;
+            let value = value
+                .map(|value| value.to_string().parse::<Node>())
+                .transpose()?;
+
+            let (existed, exists) = match staged.get(&key) {
+                Some((existed, value)) => (*existed, value.is_some()),
+                None => {
+                    let exists = self.contains_key(&key);
+                    (exists, exists)
+                },
+            };
+
+            match op {
+                SMTOp::Add(..) if exists => {
+                    return Err(SMTError::KeyAlreadyExist(key.to_string()));
+                },
+                SMTOp::Update(..) | SMTOp::Delete(..) if !exists => {
+                    return Err(SMTError::KeyDoesNotExist(key.to_string()));
+                },
+                _ => {},
+            }
+
+            staged.insert(key, (existed, value));
+        }
+
+        if staged.is_empty() {
+            return Ok(());
+        }
+
+        self.flush_cache();
+
+        if let Some(bloom_filter) = &mut self.bloom_filter {
+            for (key, (existed, value)) in &staged {
+                match (existed, value) {
+                    (false, Some(_)) => bloom_filter.insert(key),
+                    (true, None) => bloom_filter.remove(key),
+                    _ => {},
+                }
+            }
+        }
+
+        let changes = staged
+            .into_iter()
+            .map(|(key, (_, value))| {
+                let path = key_path(&key);
+                (key, path, value)
+            })
+            .collect();
+
+        self.root = self.apply_changes(self.root.clone(), 0, changes)?;
+
+        Ok(())
+    }
+
+    /// Applies the given changes to the subtree of the given node and returns its new root.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The root of the subtree.
+    /// * `depth` - The depth of the node.
+    /// * `changes` - The changes of the entries whose path goes through the node.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new root of the subtree.
+    fn apply_changes(
+        &mut self,
+        node: Node,
+        depth: usize,
+        changes: Vec<Change>,
+    ) -> Result<Node, SMTError> {
+        if changes.is_empty() {
+            return Ok(node);
+        }
+
+        if node == self.zero_node {
+            let entries = self.insert_changes(changes)?;
+
+            return self.build_subtree(depth, entries);
+        }
+
+        let child_nodes = self
+            .nodes
+            .get(&node)
+            .cloned()
+            .ok_or_else(|| SMTError::CorruptNode(node.to_string()))?;
+
+        // If the node is an entry, the subtree is rebuilt with the entry, unless it is changed,
+        // and the new entries.
+        if child_nodes.get(2).is_some() {
+            let key = &child_nodes[0];
+            let mut entries = Vec::new();
+
+            if changes.iter().any(|(changed_key, ..)| changed_key == key) {
+                self.nodes.remove(&node);
+                if let Some(values) = &mut self.values {
+                    values.remove(key);
+                }
+            } else {
+                entries.push((key_path(key), node));
+            }
+
+            entries.extend(self.insert_changes(changes)?);
+
+            return self.build_subtree(depth, entries);
+        }
+
+        let (left_changes, right_changes) = changes
+            .into_iter()
+            .partition(|(_, path, _)| path[depth] == 0);
+
+        let left = self.apply_changes(child_nodes[0].clone(), depth + 1, left_changes)?;
+        let right = self.apply_changes(child_nodes[1].clone(), depth + 1, right_changes)?;
+        let new_node = self.join_children(left, right)?;
+
+        if new_node != node {
+            self.nodes.remove(&node);
+        }
+
+        Ok(new_node)
+    }
+
+    /// Inserts the entries of the given changes that are not deletions.
+    ///
+    /// # Arguments
+    ///
+    /// * `changes` - The changes to insert.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the paths and the nodes of the inserted entries.
+    fn insert_changes(
+        &mut self,
+        changes: Vec<Change>,
+    ) -> Result<Vec<(Vec<usize>, Node)>, SMTError> {
+        let mut entries = Vec::new();
+
+        for (key, path, value) in changes {
+            if let Some(value) = value {
+                entries.push((path, self.insert_entry(key, value)?));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Builds the subtree containing only the given entries and returns its root.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The depth of the root of the subtree.
+    /// * `entries` - The paths and the nodes of the entries.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the root of the subtree.
+    fn build_subtree(
+        &mut self,
+        depth: usize,
+        mut entries: Vec<(Vec<usize>, Node)>,
+    ) -> Result<Node, SMTError> {
+        match entries.len() {
+            0 => return Ok(self.zero_node.clone()),
+            1 => return Ok(entries.pop().unwrap().1),
+            _ => {},
+        }
+
+        let (left_entries, right_entries) =
+            entries.into_iter().partition(|(path, _)| path[depth] == 0);
+
+        let left = self.build_subtree(depth + 1, left_entries)?;
+        let right = self.build_subtree(depth + 1, right_entries)?;
+
+        self.join_children(left, right)
+    }
+
+    /// Returns the node whose child nodes are the given nodes.
+    ///
+    /// A subtree with a single entry is replaced by the entry itself, as in `delete`.
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - The left child node.
+    /// * `right` - The right child node.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the parent node.
+    fn join_children(&mut self, left: Node, right: Node) -> Result<Node, SMTError> {
+        if left == self.zero_node && (right == self.zero_node || self.is_leaf(&right)) {
+            return Ok(right);
+        }
+
+        if right == self.zero_node && self.is_leaf(&left) {
+            return Ok(left);
+        }
+
+        let child_nodes = vec![left, right];
+        let node = (self.hash)(child_nodes.clone());
+        self.insert_node(node.clone(), child_nodes)?;
+
+        Ok(node)
+    }
+
+    /// Removes all the entries of the SMT, keeping its configuration and the allocated memory.
+    ///
+    /// The hash function, the big numbers flag, the zero node, the entry mark, the cache, the
+    /// separate values storage and the Bloom filter, if any, are kept, and the root is reset to
+    /// the zero node.
+    pub fn clear(&mut self) {
+        self.flush_cache();
+        self.nodes.clear();
+        if let Some(values) = &mut self.values {
+            values.clear();
+        }
+        if let Some(bloom_filter) = &mut self.bloom_filter {
+            bloom_filter.clear();
+        }
+        self.root = self.zero_node.clone();
+    }
+
+    /// Returns the depth of the entry associated with the given key, i.e. the number of siblings
+    /// in its proof.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the entry.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the depth of the entry, or `None` if the key does not exist.
+    pub fn entry_depth(&self, key: &Key) -> Option<usize> {
+        let key = self.normalize_key(key.clone()).ok()?;
+
+        let EntryResponse {
+            entry, siblings, ..
+        } = self.retrieve_entry(key);
+
+        entry.get(1).map(|_| siblings.len())
+    }
+
+    /// Returns the average depth of the entries of the SMT.
+    ///
+    /// # Returns
+    ///
+    /// The average depth of the entries, or `0.0` if the tree is empty.
+    pub fn average_depth(&self) -> f64 {
+        let depths: Vec<usize> = self
+            .nodes
+            .values()
+            .filter(|child_nodes| self.is_entry(child_nodes))
+            .filter_map(|entry| self.entry_depth(&entry[0]))
+            .collect();
+
+        if depths.is_empty() {
+            return 0.0;
+        }
+
+        depths.iter().sum::<usize>() as f64 / depths.len() as f64
+    }
+
+    /// Checks that the nodes of the SMT are consistent with its root, e.g. after loading a tree.
+    ///
+    /// Starting from the root, every node must be stored and must be the hash of its child
+    /// nodes, i.e. two child nodes for an internal node and an entry for a leaf. Every stored
+    /// node must also be reachable from the root.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the tree is consistent, or a `CorruptNode` error with the
+    /// first inconsistent node.
+    pub fn audit(&self) -> Result<(), SMTError> {
+        let mut stack = Vec::new();
+        if self.root != self.zero_node {
+            stack.push(&self.root);
+        }
+
+        let mut visited_nodes = 0;
+
+        while let Some(node) = stack.pop() {
+            let child_nodes = self
+                .nodes
+                .get(node)
+                .ok_or_else(|| SMTError::CorruptNode(node.to_string()))?;
+
+            if (!self.is_entry(child_nodes) && child_nodes.len() != 2)
+                || (self.hash)(child_nodes.clone()) != *node
+            {
+                return Err(SMTError::CorruptNode(node.to_string()));
+            }
+
+            visited_nodes += 1;
+
+            if !self.is_entry(child_nodes) {
+                stack.extend(child_nodes.iter().filter(|node| **node != self.zero_node));
+            }
+        }
+
+        if visited_nodes != self.nodes.len() {
+            // Reports one of the unreachable nodes.
+            let mut reachable_nodes = HashSet::new();
+            let mut stack = vec![&self.root];
+            while let Some(node) = stack.pop() {
+                if let Some(child_nodes) = self.nodes.get(node) {
+                    reachable_nodes.insert(node);
+                    if !self.is_entry(child_nodes) {
+                        stack.extend(child_nodes);
+                    }
+                }
+            }
+
+            let node = self
+                .nodes
+                .keys()
+                .find(|node| !reachable_nodes.contains(node))
+                .unwrap();
+
+            return Err(SMTError::CorruptNode(node.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the keys of the entries whose path starts with the given bits, i.e. the keys of
+    /// the subtree reached by following the bits from the root.
+    ///
+    /// Paths start from the least significant bit of the keys, so for example the prefix `[1]`
+    /// selects the odd keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix_bits` - The first bits of the path, each bit being 0 (left) or 1 (right).
+    ///
+    /// # Returns
+    ///
+    /// The keys of the subtree, ordered by path. There are no keys if the prefix is longer than
+    /// the paths.
+    pub fn keys_with_prefix(&self, prefix_bits: &[usize]) -> Vec<Key> {
+        if prefix_bits.len() > PATH_LENGTH {
+            return Vec::new();
+        }
+
+        let mut node = &self.root;
+
+        for (i, &bit) in prefix_bits.iter().enumerate() {
+            let child_nodes = match self.nodes.get(node) {
+                Some(child_nodes) => child_nodes,
+                None => return Vec::new(),
+            };
+
+            // An entry is the only entry of its subtree, so it is selected if the rest of its
+            // path matches the prefix.
+            if self.is_entry(child_nodes) {
+                let path = key_path(&child_nodes[0]);
+                let matches = prefix_bits[i..]
+                    .iter()
+                    .zip(&path[i..])
+                    .all(|(&bit, &path_bit)| (bit != 0) == (path_bit != 0));
+
+                return if matches {
+                    vec![child_nodes[0].clone()]
+                } else {
+                    Vec::new()
+                };
+            }
+
+            node = &child_nodes[usize::from(bit != 0)];
+        }
+
+        let mut keys = Vec::new();
+        let mut stack = vec![node];
+
+        while let Some(node) = stack.pop() {
+            if let Some(child_nodes) = self.nodes.get(node) {
+                if self.is_entry(child_nodes) {
+                    keys.push(child_nodes[0].clone());
+                } else {
+                    stack.push(&child_nodes[1]);
+                    stack.push(&child_nodes[0]);
+                }
+            }
+        }
+
+        keys
+    }
+
+    /// Returns the root of the SMT as 32 big-endian bytes.
+    ///
+    /// The root is canonicalized regardless of the node variant: a `BigInt` root is encoded by its
+    /// numeric value and a `Str` root is read as a hexadecimal number. Shorter values are left-padded
+    /// with zeros, so the root of an empty tree with the default zero node is 32 zero bytes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the root bytes, or an error if the root is negative, is not a
+    /// hexadecimal string or does not fit in 32 bytes.
+    pub fn root_bytes(&self) -> Result<[u8; 32], SMTError> {
+        let number = match &self.root {
+            Node::BigInt(number) => Some(number.clone()),
+            Node::Str(s) => BigInt::parse_bytes(s.as_bytes(), 16),
+        };
+
+        let invalid_root = || {
+            SMTError::InvalidParameterType(
+                self.root.to_string(),
+                "non-negative number of at most 32 bytes".to_string(),
+            )
+        };
+
+        let (sign, bytes) = number.ok_or_else(invalid_root)?.to_bytes_be();
+        if sign == Sign::Minus || bytes.len() > 32 {
+            return Err(invalid_root());
+        }
+
+        let mut root = [0; 32];
+        root[32 - bytes.len()..].copy_from_slice(&bytes);
+
+        Ok(root)
+    }
+
+    /// Returns an iterator over all the nodes of the SMT, both internal nodes and leaves.
+    ///
+    /// The nodes are yielded as `(hash, children)` pairs sorted by the string representation of
+    /// their hashes, so that the export is deterministic.
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the nodes of the SMT.
+    pub fn iter_nodes(&self) -> impl Iterator<Item = (Node, Vec<Node>)> + '_ {
+        let mut nodes: Vec<(String, &Node, &Vec<Node>)> = self
+            .nodes
+            .iter()
+            .map(|(node, child_nodes)| (node.to_string(), node, child_nodes))
+            .collect();
+
+        nodes.sort_by(|a, b| a.0.cmp(&b.0));
+
+        nodes
+            .into_iter()
+            .map(|(_, node, child_nodes)| (node.clone(), child_nodes.clone()))
+    }
+
+    /// Returns the number of nodes of the SMT, both internal nodes and leaves.
+    ///
+    /// # Returns
+    ///
+    /// The number of nodes of the SMT.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Creates a proof to prove the membership or the non-membership of a tree entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to create the proof for.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MerkleProof`, or an error if the key is not valid.
+    pub fn create_proof(&self, key: Key) -> Result<MerkleProof, SMTError> {
+        let key = self.normalize_key(key)?;
+        let path = key_path(&key);
+
+        Ok(self.create_proof_with_path(key, &path))
+    }
+
+    /// Creates a proof of membership or non-membership for the given raw bytes key in the SMT.
+    ///
+    /// The proof is the same as the one returned by `create_proof` for the hexadecimal encoding
+    /// of the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to create the proof for, a non-empty big-endian byte array of at most 32 bytes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MerkleProof`, or an error if the key is not a valid byte array.
+    pub fn create_proof_bytes(&self, key: &[u8]) -> Result<MerkleProof, SMTError> {
+        let (key, path) = self.bytes_key(key)?;
+
+        Ok(self.create_proof_with_path(key, &path))
+    }
+
+    /// Creates a proof of membership or non-membership for the given key and path in the SMT.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The normalized key to create the proof for.
+    /// * `path` - The path of the key.
+    ///
+    /// # Returns
+    ///
+    /// A `MerkleProof` containing the proof information.
+    fn create_proof_with_path(&self, key: Key, path: &[usize]) -> MerkleProof {
+        let EntryResponse {
+            entry,
+            matching_entry,
+            siblings,
+        } = self.retrieve_entry_with_path(key, path);
+
+        // If the key exists, the function returns a proof with the entry itself, otherwise it returns
+        // a non-membership proof with the matching entry.
+        MerkleProof {
+            entry_response: EntryResponse {
+                entry: entry.clone(),
+                matching_entry,
+                siblings,
+            },
+            root: self.root.clone(),
+            membership: entry.get(1).is_some(),
+            zero_siblings: Vec::new(),
+        }
+    }
+
+    /// Verifies a membership or a non-membership proof for a given key in the SMT.
+    ///
+    /// # Arguments
+    ///
+    /// * `merkle_proof` - The Merkle proof to verify.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the proof is valid or not.
+    pub fn verify_proof(&self, merkle_proof: MerkleProof) -> bool {
+        // Paths are 256 bits long, so a proof cannot have more siblings.
+        if merkle_proof.entry_response.siblings.len() + merkle_proof.zero_siblings.len()
+            > PATH_LENGTH
+        {
+            return false;
+        }
+
+        let merkle_proof = merkle_proof.decompress(&self.zero_node);
+
+        let EntryResponse {
+            entry,
+            matching_entry,
+            siblings,
+        } = &merkle_proof.entry_response;
+
+        // The membership flag must agree with the entry, and an entry with a value must be a
+        // well-formed leaf, so that an internal node cannot be presented as an entry.
+        if entry.is_empty()
+            || merkle_proof.membership != entry.get(1).is_some()
+            || (merkle_proof.membership && !self.is_entry(entry))
+        {
+            return false;
+        }
+
+        // If there is no matching entry, it simply obtains the root hash by using the siblings and the
+        // path of the key.
+        // The keys of the proof are not trusted, so a key that cannot be converted to a path
+        // makes the proof invalid.
+        let Ok(path) = checked_key_path(&entry[0]) else {
+            return false;
+        };
+
+        if matching_entry.is_none() {
+            // If there is not an entry value, the proof is a non-membership proof. In this case, since there
+            // is not a matching entry, the node is set to a zero node. If there is an entry value, the proof
+            // is a membership proof and the node is set to the hash of the entry.
+            let node = if merkle_proof.membership {
+                (self.hash)(entry.clone())
+            } else {
+                self.zero_node.clone()
+            };
+            let root = self.calculate_root(node, &path, siblings);
+
+            // If the obtained root is equal to the proof root, then the proof is valid.
+            return root == merkle_proof.root;
+        }
+
+        // If there is a matching entry, the proof is definitely a non-membership proof. In this case, it checks
+        // if the matching node belongs to the tree, and then it checks if the number of the first matching bits
+        // of the keys is greater than or equal to the number of the siblings.
+        if let Some(matching_entry) = matching_entry {
+            // The matching entry must be a leaf with a key different from the non-member key, otherwise
+            // the proof of a member could be turned into a non-membership proof.
+            if merkle_proof.membership
+                || !self.is_entry(matching_entry)
+                || matching_entry[0] == entry[0]
+            {
+                return false;
+            }
+
+            let Ok(matching_path) = checked_key_path(&matching_entry[0]) else {
+                return false;
+            };
+            let node = (self.hash)(matching_entry.to_vec());
+            let root = self.calculate_root(node, &matching_path, siblings);
+
+            if root == merkle_proof.root {
+                // Returns the first common bits of the two keys: the non-member key and the matching key.
+                let first_matching_bits = get_first_common_elements(&path, &matching_path);
+
+                // If the non-member key was a key of a tree entry, the depth of the matching node should be
+                // greater than the number of the fisrt matching bits. Otherwise, the depth of the node can be
+                // defined by the number of its siblings.
+                return siblings.len() <= first_matching_bits.len();
+            }
+        }
+
+        false
+    }
+
+    /// Verifies a membership or a non-membership proof against the current root of the SMT.
+    ///
+    /// Unlike `verify_proof`, which checks the proof against the root it was created with, a proof
+    /// created before the tree was modified is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `merkle_proof` - The Merkle proof to verify.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the proof is valid for the current tree or not.
+    pub fn verify_proof_current(&self, merkle_proof: MerkleProof) -> bool {
+        merkle_proof.root == self.root && self.verify_proof(merkle_proof)
+    }
+
+    /// Verifies a set of membership or non-membership proofs against a known root, without the tree.
+    ///
+    /// The proofs are verified as `verify_proof` would on the tree built by `verifier`, so it must
+    /// be configured with the hash function, mode, zero node and entry mark of the tree, and each
+    /// proof must also have been created with the given root.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The trusted root of the tree.
+    /// * `verifier` - The builder configured as the one of the tree.
+    /// * `proofs` - The Merkle proofs to verify.
+    ///
+    /// # Returns
+    ///
+    /// A vector of booleans indicating whether each proof is valid or not, in the same order.
+    pub fn verify_against_root(
+        root: &Node,
+        verifier: SMTBuilder,
+        proofs: &[MerkleProof],
+    ) -> Vec<bool> {
+        let smt = verifier.build();
+
+        proofs
+            .iter()
+            .map(|proof| proof.root == *root && smt.verify_proof(proof.clone()))
+            .collect()
+    }
+
+    /// Creates a proof to prove the non-membership of several keys at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - The keys to create the proof for.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `BatchNonMembershipProof`, or an error if any of the keys exists
+    /// or is not valid.
+    pub fn create_non_membership_batch(
+        &self,
+        keys: &[Key],
+    ) -> Result<BatchNonMembershipProof, SMTError> {
+        let mut siblings = Vec::new();
+        let mut sibling_indexes = HashMap::new();
+        let mut entries = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            let MerkleProof {
+                entry_response,
+                membership,
+                ..
+            } = self.create_proof(key.clone())?;
+
+            if membership {
+                return Err(SMTError::KeyAlreadyExist(
+                    entry_response.entry[0].to_string(),
+                ));
+            }
+
+            let indexes = entry_response
+                .siblings
+                .into_iter()
+                .map(|sibling| {
+                    *sibling_indexes.entry(sibling.clone()).or_insert_with(|| {
+                        siblings.push(sibling);
+                        siblings.len() - 1
+                    })
+                })
+                .collect();
+
+            entries.push(NonMembershipEntry {
+                key: entry_response.entry[0].clone(),
+                matching_entry: entry_response.matching_entry,
+                siblings: indexes,
+            });
+        }
+
+        Ok(BatchNonMembershipProof {
+            root: self.root.clone(),
+            siblings,
+            entries,
+        })
+    }
+
+    /// Verifies a proof of the non-membership of several keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_proof` - The batch proof to verify.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the non-membership of all the keys is proven or not.
+    pub fn verify_non_membership_batch(&self, batch_proof: &BatchNonMembershipProof) -> bool {
+        batch_proof.entries.iter().all(|entry| {
+            let siblings: Option<Siblings> = entry
+                .siblings
+                .iter()
+                .map(|&i| batch_proof.siblings.get(i).cloned())
+                .collect();
+
+            let Some(siblings) = siblings else {
+                return false;
+            };
+
+            self.verify_proof(MerkleProof {
+                entry_response: EntryResponse {
+                    entry: vec![entry.key.clone()],
+                    matching_entry: entry.matching_entry.clone(),
+                    siblings,
+                },
+                root: batch_proof.root.clone(),
+                membership: false,
+                zero_siblings: Vec::new(),
+            })
+        })
+    }
+
+    /// Retrieves the entry associated with the given key from the SMT.
+    ///
+    /// If the key passed as parameter exists in the SMT, the function returns the entry itself, otherwise
+    /// it returns the entry with only the key. When there is another matching entry in the same path, it
+    /// returns the matching entry as well.
+    ///
+    /// In any case, the function returns the siblings of the path.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to retrieve the entry for.
+    ///
+    /// # Returns
+    ///
+    /// An `EntryResponse` struct containing the entry, the matching entry (if any), and the siblings of the leaf node.
+    fn retrieve_entry(&self, key: Key) -> EntryResponse {
+        let path = key_path(&key);
+
+        self.retrieve_entry_with_path(key, &path)
+    }
+
+    /// Retrieves the entry associated with the given key and path from the SMT.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to retrieve the entry for.
+    /// * `path` - The path of the key.
+    ///
+    /// # Returns
+    ///
+    /// An `EntryResponse` struct containing the entry, the matching entry (if any), and the siblings of the leaf node.
+    fn retrieve_entry_with_path(&self, key: Key, path: &[usize]) -> EntryResponse {
+        let mut siblings: Siblings = Vec::new();
+        let mut node = self.root.clone();
+
+        let mut i = 0;
+
+        // Starting from the root, it traverses the tree until it reaches a leaf node, a zero node,
+        // or a matching entry.
+        while node != self.zero_node {
+            let child_nodes = self.nodes.get(&node).unwrap_or(&Vec::new()).clone();
+
+            // If the third element of the child nodes is not None, it means that the node is an entry of the tree.
+            if child_nodes.get(2).is_some() {
+                if child_nodes[0] == key {
+                    // An entry is found with the same key, and it returns it with the siblings.
+                    return EntryResponse {
+                        entry: child_nodes,
+                        matching_entry: None,
+                        siblings,
+                    };
+                }
+
+                // An entry was found with a different key, but the key of this particular entry matches the first 'i'
+                // bits of the key passed as parameter. It can be useful in several functions.
+                return EntryResponse {
+                    entry: vec![key.clone()],
+                    matching_entry: Some(child_nodes),
+                    siblings,
+                };
+            }
+
+            // When it goes down into the tree and follows the path, in every step a node is chosen between left
+            // and right child nodes, and the opposite node is saved in the `siblings` vector. The direction
+            // is only read here, since an entry can be found at depth 256, past the last bit of the path.
+            let direction = path[i];
+            node = child_nodes[direction].clone();
+            siblings.push(child_nodes[1 - direction].clone());
+
+            i += 1;
+        }
+
+        // The path led to a zero node.
+        EntryResponse {
+            entry: vec![key],
+            matching_entry: None,
+            siblings,
+        }
+    }
+
+    /// Calculates the root of the tree by using the given node, the path, and the siblings.
+    ///
+    /// It calculates with a bottom up approach by starting from the node and going up to the root.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The node to start the calculation from.
+    /// * `path` - The path of the key.
+    /// * `siblings` - The siblings of the path.
+    ///
+    /// # Returns
+    ///
+    /// The root of the tree.
+    fn calculate_root(&self, mut node: Node, path: &[usize], siblings: &Siblings) -> Node {
+        for i in (0..siblings.len()).rev() {
+            let child_nodes: ChildNodes = if path[i] != 0 {
+                vec![siblings[i].clone(), node.clone()]
+            } else {
+                vec![node.clone(), siblings[i].clone()]
+            };
+
+            node = (self.hash)(child_nodes);
+        }
+
+        node
+    }
+
+    /// Adds new nodes to the tree with the new hashes.
+    ///
+    /// It starts with a bottom up approach until it reaches the root of the tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The node to start the calculation from.
+    /// * `path` - The path of the key.
+    /// * `siblings` - The siblings of the path.
+    /// * `i` - The index of the sibling to start from.
+    ///
+    /// # Returns
+    ///
+    /// The new root of the tree.
+    fn add_new_nodes(
+        &mut self,
+        mut node: Node,
+        path: &[usize],
+        siblings: &Siblings,
+        i: Option<isize>,
+    ) -> Result<Node, SMTError> {
+        let mut starting_index = if let Some(i) = i {
+            i
+        } else {
+            siblings.len() as isize - 1
+        };
+
+        while starting_index >= 0 {
+            if siblings.get(starting_index as usize).is_none() {
+                return Err(SMTError::InvalidSiblingIndex);
+            }
+
+            let child_nodes: ChildNodes = if path[starting_index as usize] != 0 {
+                vec![siblings[starting_index as usize].clone(), node.clone()]
+            } else {
+                vec![node.clone(), siblings[starting_index as usize].clone()]
+            };
+
+            node = (self.hash)(child_nodes.clone());
+
+            self.insert_node(node.clone(), child_nodes)?;
+
+            starting_index -= 1;
+        }
+
+        Ok(node)
+    }
+
+    /// Deletes the old nodes of the tree.
+    ///
+    /// It starts with a bottom up approach until it reaches the root of the tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The node to start the calculation from.
+    /// * `path` - The path of the key.
+    /// * `siblings` - The siblings of the path.
+    fn delete_old_nodes(&mut self, mut node: Node, path: &[usize], siblings: &Siblings) {
+        for i in (0..siblings.len()).rev() {
+            let child_nodes: ChildNodes = if path[i] != 0 {
+                vec![siblings[i].clone(), node.clone()]
+            } else {
+                vec![node.clone(), siblings[i].clone()]
+            };
+
+            node = (self.hash)(child_nodes);
+
+            self.nodes.remove(&node);
+        }
+    }
+
+    /// Inserts a new entry node in the tree.
+    ///
+    /// If the values are stored separately, the entry holds the digest of the value and the value
+    /// is saved in the `values` map.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the entry.
+    /// * `value` - The value of the entry.
+    ///
+    /// # Returns
+    ///
+    /// The hash of the new entry node.
+    fn insert_entry(&mut self, key: Key, value: Value) -> Result<Node, SMTError> {
+        let digest = if self.values.is_some() {
+            (self.hash)(vec![value.clone()])
+        } else {
+            value.clone()
+        };
+
+        let entry = vec![key.clone(), digest, self.entry_mark.clone()];
+        let node = (self.hash)(entry.clone());
+        self.insert_node(node.clone(), entry)?;
+
+        if let Some(values) = &mut self.values {
+            values.insert(key, value);
+        }
+
+        Ok(node)
+    }
+
+    /// Inserts a node with its child nodes in the SMT.
+    ///
+    /// It checks that the node does not already exist with different child nodes, which means
+    /// that the hash function is not collision-resistant (see `SMT::capacity_estimate`). In that
+    /// case the tree may be left partially updated and should be discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The hash of the child nodes.
+    /// * `child_nodes` - The child nodes.
+    ///
+    /// # Returns
+    ///
+    /// An `Result` indicating whether the operation was successful or not.
+    fn insert_node(&mut self, node: Node, child_nodes: ChildNodes) -> Result<(), SMTError> {
+        if let Some(existing_child_nodes) = self.nodes.get(&node) {
+            if *existing_child_nodes != child_nodes {
+                return Err(SMTError::HashCollision(node.to_string()));
+            }
+        }
+
+        self.nodes.insert(node, child_nodes);
+
+        Ok(())
+    }
+
+    /// Removes all the entries from the `get` cache, if the cache is enabled.
+    fn flush_cache(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clear();
+        }
+    }
+
+    /// Converts a key to its canonical representation.
+    ///
+    /// Keys are compared by their numeric value: a `BigInt` key is a number and a `Str` key is
+    /// always a hexadecimal number, so `Key::BigInt(10)` and `Key::Str("a")` are the same key.
+    /// Note that `Node::from_str` parses a string of decimal digits as a `BigInt`, so
+    /// `"10".parse()` is the key 10 while `Key::Str("10")` is the key 16. The canonical
+    /// representation is a `BigInt` in big numbers mode and a lowercase hexadecimal string
+    /// without leading zeros otherwise, and it is the key hashed in the leaf of the entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to convert.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the canonical key, or an error if the key is not a non-negative
+    /// number or a hexadecimal string of at most 256 bits.
+    fn normalize_key(&self, key: Key) -> Result<Key, SMTError> {
+        let number = match &key {
+            Node::BigInt(number) => Some(number.clone()),
+            Node::Str(s) if !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit()) => {
+                BigInt::parse_bytes(s.as_bytes(), 16)
+            },
+            Node::Str(_) => None,
+        };
+
+        let number = match number {
+            Some(number) if number.sign() != Sign::Minus && number.bits() <= PATH_LENGTH as u64 => {
+                number
+            },
+            _ => {
+                return Err(SMTError::InvalidParameterType(
+                    key.to_string(),
+                    "non-negative number or hexadecimal string of at most 256 bits".to_string(),
+                ))
+            },
+        };
+
+        Ok(self.canonical_key(number))
+    }
+
+    /// Converts a non-negative number to the canonical representation of the key with that value,
+    /// see `normalize_key`.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The value of the key.
+    ///
+    /// # Returns
+    ///
+    /// The canonical key.
+    fn canonical_key(&self, number: BigInt) -> Key {
+        if self.big_numbers {
+            Node::BigInt(number)
+        } else {
+            Node::Str(number.to_str_radix(16))
+        }
+    }
+
+    /// Checks if the given child nodes are a well-formed entry of the tree, i.e. a key, a value,
+    /// and the entry mark.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The child nodes to check.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the child nodes are an entry or not.
+    fn is_entry(&self, entry: &[Node]) -> bool {
+        entry.len() == 3 && entry[2] == self.entry_mark
+    }
+
+    /// Checks if the given node is a leaf node or not.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The node to check.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the node is a leaf node or not.
+    fn is_leaf(&self, node: &Node) -> bool {
+        if let Some(child_nodes) = self.nodes.get(node) {
+            child_nodes.get(2).is_some()
+        } else {
+            false
+        }
+    }
+}
+
+/// Locks the `get` cache. If a thread panicked while holding the lock, the cache is cleared,
+/// since it only holds copies of values of the tree.
+///
+/// # Arguments
+///
+/// * `cache` - The cache to lock.
+///
+/// # Returns
+///
+/// The guard of the cache.
+fn lock_cache(cache: &Mutex<LruCache<Key, Value>>) -> MutexGuard<'_, LruCache<Key, Value>> {
+    cache.lock().unwrap_or_else(|error| {
+        cache.clear_poison();
+        let mut cache = error.into_inner();
+        cache.clear();
+        cache
+    })
+}
+
+/// Converts a key to its path, the key being a number or a hexadecimal string.
+///
+/// Non-negative numbers of at most 32 bytes, i.e. all the keys of the binary key functions in
+/// big numbers mode, are converted from their bytes without any string conversion.
+///
+/// # Arguments
+///
+/// * `key` - The key to convert.
+///
+/// # Returns
+///
+/// The path represented as a vector of usize.
+fn key_path(key: &Key) -> Vec<usize> {
+    match key {
+        Node::BigInt(number) => match number.to_bytes_be() {
+            (Sign::Minus, _) => key_to_path(&number.to_str_radix(16)),
+            (_, bytes) if bytes.len() > 32 => key_to_path(&number.to_str_radix(16)),
+            (_, bytes) => bytes_to_path(&bytes),
+        },
+        Node::Str(s) => key_to_path(s),
+    }
+}
+
+/// Converts a key to its path like `key_path`, checking first that the key can be converted.
+///
+/// It is used for the keys of proofs, which are not trusted, so that a malformed proof is
+/// rejected instead of making the conversion panic.
+///
+/// # Arguments
+///
+/// * `key` - The key to convert.
+///
+/// # Returns
+///
+/// A `Result` containing the path, or an error if the key is not a non-negative number or a
+/// hexadecimal string.
+fn checked_key_path(key: &Key) -> Result<Vec<usize>, SMTError> {
+    let is_valid = match key {
+        Node::BigInt(number) => number.sign() != Sign::Minus,
+        Node::Str(s) => !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit()),
+    };
+
+    if !is_valid {
+        return Err(SMTError::InvalidParameterType(
+            key.to_string(),
+            "non-negative number or hexadecimal string".to_string(),
+        ));
+    }
+
+    Ok(key_path(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_function(nodes: Vec<Node>) -> Node {
+        let strings: Vec<String> = nodes.iter().map(|node| node.to_string()).collect();
+        Node::Str(strings.join(","))
+    }
+
+    #[test]
+    fn test_new() {
+        let smt = SMT::new(hash_function, false);
+        assert!(!smt.big_numbers);
+        assert_eq!(smt.zero_node, Node::Str("0".to_string()));
+        assert_eq!(smt.entry_mark, Node::Str("1".to_string()));
+        assert_eq!(smt.nodes, HashMap::new());
+        assert_eq!(smt.root, Node::Str("0".to_string()));
+
+        let smt = SMT::new(hash_function, true);
+        assert!(smt.big_numbers);
+        assert_eq!(smt.zero_node, Node::BigInt(BigInt::from(0)));
+        assert_eq!(smt.entry_mark, Node::BigInt(BigInt::from(1)));
+        assert_eq!(smt.nodes, HashMap::new());
+        assert_eq!(smt.root, Node::BigInt(BigInt::from(0)));
+    }
+
+    #[test]
+    fn test_entry_mark() {
+        let mark_a = Node::Str("a".to_string());
+        let mark_b = Node::Str("b".to_string());
+        let mut smt_a = SMT::new_with_mark(hash_function, false, mark_a.clone());
+        let mut smt_b = SMT::builder(hash_function, false)
+            .entry_mark(mark_b.clone())
+            .build();
+        assert_eq!(smt_a.entry_mark, mark_a);
+
+        for key in ["11", "21", "aaa"] {
+            let key = Key::Str(key.to_string());
+            let value = Value::Str("bbb".to_string());
+            smt_a.add(key.clone(), value.clone()).unwrap();
+            smt_b.add(key, value).unwrap();
+        }
+
+        assert_ne!(smt_a.root, smt_b.root);
+
+        let key = Key::Str("21".to_string());
+        assert_eq!(smt_a.get(key.clone()), Some(Value::Str("bbb".to_string())));
+        assert!(smt_a.verify_proof(smt_a.create_proof(key.clone()).unwrap()));
+        assert!(smt_b.verify_proof(smt_b.create_proof(key.clone()).unwrap()));
+        assert!(!smt_b.verify_proof(smt_a.create_proof(key.clone()).unwrap()));
+        assert!(!smt_a.verify_proof(smt_b.create_proof(key.clone()).unwrap()));
+
+        smt_a
+            .update(key.clone(), Value::Str("ccc".to_string()))
+            .unwrap();
+        assert!(smt_a.verify_proof(smt_a.create_proof(key.clone()).unwrap()));
+
+        smt_a.delete(key.clone()).unwrap();
+        assert_eq!(smt_a.get(key.clone()), None);
+        assert!(smt_a.verify_proof(smt_a.create_proof(key).unwrap()));
+    }
+
+    #[test]
+    fn test_zero_node() {
+        let zero_node = Node::Str("ff".to_string());
+        let mut smt = SMT::new_with_zero(hash_function, false, zero_node.clone());
+
+        assert_eq!(smt.zero_node, zero_node);
+        assert_eq!(smt.root, zero_node);
+
+        let keys = ["1", "2", "3", "6", "a"];
+        for key in keys {
+            smt.add(Key::Str(key.to_string()), Value::Str(key.repeat(2)))
+                .unwrap();
+        }
+
+        // The zero node is used for the empty subtrees.
+        let proof = smt.create_proof(Key::Str("2".to_string())).unwrap();
+        assert!(proof.entry_response.siblings.contains(&zero_node));
+        assert!(smt.verify_proof(proof.clone()));
+        assert!(smt.verify_proof(proof.compress(&zero_node)));
+
+        let proof = smt.create_proof(Key::Str("4".to_string())).unwrap();
+        assert!(!proof.membership);
+        assert!(smt.verify_proof(proof));
+
+        for key in keys {
+            let proof = smt.create_proof(Key::Str(key.to_string())).unwrap();
+            assert!(proof.membership);
+            assert!(smt.verify_proof(proof));
+
+            smt.delete(Key::Str(key.to_string())).unwrap();
+        }
+
+        assert_eq!(smt.root, zero_node);
+        assert!(smt.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_builder() {
+        let zero_node = Node::BigInt(BigInt::from(7));
+        let entry_mark = Node::BigInt(BigInt::from(9));
+        let mut smt = SMT::builder(hash_function, true)
+            .zero_node(zero_node.clone())
+            .entry_mark(entry_mark.clone())
+            .cache(4)
+            .separate_values()
+            .bloom_filter(64, 2)
+            .build();
+
+        assert_eq!(smt.root, zero_node);
+        assert_eq!(smt.entry_mark, entry_mark);
+        assert!(smt.cache.is_some());
+        assert!(smt.values.is_some());
+        assert!(smt.bloom_filter.is_some());
+
+        for i in [1, 2, 5] {
+            smt.add(
+                Key::BigInt(BigInt::from(i)),
+                Value::BigInt(BigInt::from(i * 10)),
+            )
+            .unwrap();
+        }
+
+        let key = Key::BigInt(BigInt::from(2));
+        assert_eq!(smt.get(key.clone()), Some(Value::BigInt(BigInt::from(20))));
+        assert!(smt.contains_key(&key));
+        assert!(!smt.contains_key(&Key::BigInt(BigInt::from(3))));
+        assert!(smt.verify_proof(smt.create_proof(key).unwrap()));
+
+        // The options that are not set keep the values of `new`.
+        let smt = SMT::builder(hash_function, false).build();
+        let default_smt = SMT::new(hash_function, false);
+        assert_eq!(smt.zero_node, default_smt.zero_node);
+        assert_eq!(smt.entry_mark, default_smt.entry_mark);
+        assert!(smt.cache.is_none() && smt.values.is_none() && smt.bloom_filter.is_none());
+    }
+
+    #[test]
+    fn test_root_of() {
+        assert_eq!(
+            SMT::root_of(hash_function, true, &[]),
+            Ok(Node::BigInt(BigInt::from(0)))
+        );
+
+        let entries: Vec<(Key, Value)> = (1..6)
+            .map(|i| {
+                (
+                    Key::BigInt(BigInt::from(i)),
+                    Value::BigInt(BigInt::from(i * 10)),
+                )
+            })
+            .collect();
+
+        let mut smt = SMT::new(hash_function, true);
+        for (key, value) in &entries {
+            smt.add(key.clone(), value.clone()).unwrap();
+        }
+
+        assert_eq!(SMT::root_of(hash_function, true, &entries), Ok(smt.root));
+
+        let mut entries = entries;
+        entries.push(entries[0].clone());
+        assert_eq!(
+            SMT::root_of(hash_function, true, &entries),
+            Err(SMTError::KeyAlreadyExist("1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_key_normalization() {
+        for big_numbers in [true, false] {
+            let smt = SMT::new(hash_function, big_numbers);
+
+            let canonical_key = smt.normalize_key(Key::BigInt(BigInt::from(10))).unwrap();
+            for key in ["10", "a", "0a", "A", "00A"] {
+                assert_eq!(
+                    smt.normalize_key(key.parse().unwrap()).unwrap(),
+                    canonical_key
+                );
+            }
+
+            assert_eq!(key_path(&canonical_key), key_to_path("a"));
+        }
+
+        let smt = SMT::new(hash_function, true);
+        assert_eq!(
+            smt.normalize_key(Key::Str("ff".to_string())).unwrap(),
+            Key::BigInt(BigInt::from(255))
+        );
+
+        let smt = SMT::new(hash_function, false);
+        assert_eq!(
+            smt.normalize_key(Key::BigInt(BigInt::from(255))).unwrap(),
+            Key::Str("ff".to_string())
+        );
+
+        for big_numbers in [true, false] {
+            let mut smt = SMT::new(hash_function, big_numbers);
+            smt.add("10".parse().unwrap(), Value::Str("b".to_string()))
+                .unwrap();
+            smt.add(Key::Str("3".to_string()), Value::Str("c".to_string()))
+                .unwrap();
+
+            assert_eq!(
+                smt.get(Key::Str("a".to_string())),
+                Some(Value::Str("b".to_string()))
+            );
+            assert_eq!(
+                smt.get(Key::BigInt(BigInt::from(3))),
+                Some(Value::Str("c".to_string()))
+            );
+            assert!(smt
+                .add(Key::Str("0A".to_string()), Value::Str("d".to_string()))
+                .is_err());
+
+            let proof = smt.create_proof(Key::Str("a".to_string())).unwrap();
+            assert!(proof.membership);
+            assert!(smt.verify_proof(proof));
+
+            smt.delete(Key::BigInt(BigInt::from(10))).unwrap();
+            assert_eq!(smt.get(Key::Str("a".to_string())), None);
+        }
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let invalid_keys = [
+            Key::Str("xyz".to_string()),
+            Key::Str("".to_string()),
+            Key::Str("-a".to_string()),
+            Key::Str("+a".to_string()),
+            Key::Str("f".repeat(65)),
+            Key::BigInt(BigInt::from(-1)),
+            Key::BigInt(BigInt::from(1) << 256),
+        ];
+
+        for big_numbers in [true, false] {
+            let mut smt = SMT::new(hash_function, big_numbers);
+            smt.add(Key::Str("a".to_string()), Value::Str("b".to_string()))
+                .unwrap();
+            let root = smt.root.clone();
+
+            for key in &invalid_keys {
+                let error = SMTError::InvalidParameterType(
+                    key.to_string(),
+                    "non-negative number or hexadecimal string of at most 256 bits".to_string(),
+                );
+
+                assert_eq!(
+                    smt.add(key.clone(), Value::Str("c".to_string()))
+                        .unwrap_err(),
+                    error
+                );
+                assert_eq!(
+                    smt.update(key.clone(), Value::Str("c".to_string()))
+                        .unwrap_err(),
+                    error
+                );
+                assert_eq!(
+                    smt.upsert(key.clone(), Value::Str("c".to_string()))
+                        .unwrap_err(),
+                    error
+                );
+                assert_eq!(smt.delete(key.clone()).unwrap_err(), error);
+                assert_eq!(smt.apply(&[SMTOp::Delete(key.clone())]).unwrap_err(), error);
+                assert_eq!(smt.create_proof(key.clone()).unwrap_err(), error);
+                assert_eq!(smt.get(key.clone()), None);
+                assert_eq!(smt.entry_depth(key), None);
+                assert!(!smt.contains_key(key));
+            }
+
+            assert_eq!(smt.root, root);
+        }
+
+        // The largest key of 256 bits is valid.
+        let mut smt = SMT::new(hash_function, true);
+        let key = Key::BigInt((BigInt::from(1) << 256) - 1);
+        smt.add(key.clone(), Value::BigInt(BigInt::from(1)))
+            .unwrap();
+        assert!(smt.verify_proof(smt.create_proof(key).unwrap()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        for big_numbers in [true, false] {
+            let mut smt = SMT::new(hash_function, big_numbers);
+            for i in [1, 2, 5, 12] {
+                smt.add(
+                    Key::BigInt(BigInt::from(i)),
+                    Value::BigInt(BigInt::from(i * 10)),
+                )
+                .unwrap();
+            }
+
+            let json = smt.to_json();
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(value["bigNumbers"], big_numbers);
+            assert_eq!(value["zeroNode"], "0");
+            assert_eq!(value["entryMark"], "1");
+            assert_eq!(value["root"], smt.root.to_string());
+            assert_eq!(value["nodes"].as_array().unwrap().len(), smt.node_count());
+            assert!(value.get("values").is_none());
+
+            let mut deserialized = SMT::from_json(&json, hash_function).unwrap();
+            assert_eq!(deserialized.root, smt.root);
+            assert_eq!(deserialized.nodes, smt.nodes);
+            assert_eq!(deserialized.to_json(), json);
+
+            for i in 0..16 {
+                let proof = deserialized
+                    .create_proof(Key::BigInt(BigInt::from(i)))
+                    .unwrap();
+                assert_eq!(
+                    proof,
+                    smt.create_proof(Key::BigInt(BigInt::from(i))).unwrap()
+                );
+                assert!(deserialized.verify_proof(proof));
+            }
+
+            // The tree cannot be rebuilt with another hash function.
+            fn other_hash_function(nodes: Vec<Node>) -> Node {
+                let strings: Vec<String> = nodes.iter().map(|node| node.to_string()).collect();
+                Node::Str(strings.join(";"))
+            }
+            assert!(matches!(
+                SMT::from_json(&json, other_hash_function),
+                Err(SMTError::InvalidJson(_))
+            ));
+
+            deserialized
+                .add(
+                    Key::BigInt(BigInt::from(7)),
+                    Value::BigInt(BigInt::from(70)),
+                )
+                .unwrap();
+            smt.add(
+                Key::BigInt(BigInt::from(7)),
+                Value::BigInt(BigInt::from(70)),
+            )
+            .unwrap();
+            assert_eq!(deserialized.root, smt.root);
+        }
+
+        let mut smt = SMT::builder(hash_function, true).separate_values().build();
+        smt.add(
+            Key::BigInt(BigInt::from(3)),
+            Value::BigInt(BigInt::from(30)),
+        )
+        .unwrap();
+        let deserialized = SMT::from_json(&smt.to_json(), hash_function).unwrap();
+        assert_eq!(
+            deserialized.get(Key::BigInt(BigInt::from(3))),
+            Some(Value::BigInt(BigInt::from(30)))
+        );
+
+        assert!(matches!(
+            SMT::from_json("{}", hash_function),
+            Err(SMTError::InvalidJson(_))
+        ));
+        assert!(matches!(
+            SMT::from_json(
+                r#"{"bigNumbers":true,"zeroNode":"0","entryMark":"1","root":"5","nodes":[]}"#,
+                hash_function
+            ),
+            Err(SMTError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn test_get() {
+        let mut smt = SMT::new(hash_function, false);
+        let key = Key::Str("aaa".to_string());
+        let value = Value::Str("bbb".to_string());
+        let _ = smt.add(key.clone(), value.clone());
+        let result = smt.get(key.clone());
+        assert_eq!(result, Some(value));
+
+        let key2 = Key::Str("ccc".to_string());
+        let result2 = smt.get(key2.clone());
+        assert_eq!(result2, None);
+
+        let mut smt = SMT::new(hash_function, true);
+        let key = Key::BigInt(BigInt::from(123));
+        let value = Value::BigInt(BigInt::from(456));
+        let _ = smt.add(key.clone(), value.clone());
+        let result = smt.get(key.clone());
+        assert_eq!(result, Some(value));
+    }
+    #[test]
+    fn test_contains_key() {
+        for big_numbers in [true, false] {
+            let mut smt = SMT::new(hash_function, big_numbers);
+            assert!(!smt.contains_key(&Key::BigInt(BigInt::from(1))));
+
+            for i in [1, 2, 5, 12] {
+                smt.add(Key::BigInt(BigInt::from(i)), Value::BigInt(BigInt::from(i)))
+                    .unwrap();
+            }
+
+            for i in 0..16 {
+                let expected = [1, 2, 5, 12].contains(&i);
+
+                assert_eq!(smt.contains_key(&Key::BigInt(BigInt::from(i))), expected);
+                assert_eq!(smt.contains_key(&Key::Str(format!("{:x}", i))), expected);
+            }
+
+            smt.delete(Key::BigInt(BigInt::from(5))).unwrap();
+            assert!(!smt.contains_key(&Key::BigInt(BigInt::from(5))));
+            assert!(smt.contains_key(&Key::BigInt(BigInt::from(12))));
+        }
+    }
+
+    #[test]
+    fn test_add() {
+        let mut smt = SMT::new(hash_function, false);
+        let key = Key::Str("aaa".to_string());
+        let value = Value::Str("bbb".to_string());
+        let result = smt.add(key.clone(), value.clone());
+        assert!(result.is_ok());
+        assert_eq!(smt.nodes.len(), 1);
+        assert_eq!(
+            smt.nodes.get(&smt.root),
+            Some(&vec![key.clone(), value.clone(), smt.entry_mark.clone()])
+        );
+
+        let key2 = Key::Str("def".to_string());
+        let invalid_value = Value::Str("xyz".to_string());
+        let result2 = smt.add(key2.clone(), invalid_value.clone());
+        assert_eq!(
+            result2,
+            Err(SMTError::InvalidParameterType(
+                invalid_value.to_string(),
+                "BigInt or hexadecimal string".to_string()
+            ))
+        );
+        assert_eq!(smt.get(key2), None);
+
+        let mut smt = SMT::new(hash_function, true);
+        let key = Key::BigInt(BigInt::from(123));
+        let value = Value::BigInt(BigInt::from(456));
+        let result = smt.add(key.clone(), value.clone());
+        assert!(result.is_ok());
+        assert_eq!(smt.nodes.len(), 1);
+        assert_eq!(
+            smt.nodes.get(&smt.root),
+            Some(&vec![key.clone(), value.clone(), smt.entry_mark.clone()])
+        );
+    }
+
+    #[test]
+    fn test_update() {
         let mut smt = SMT::new(hash_function, false);
         let key = Key::Str("aaa".to_string());
         let value = Value::Str("bbb".to_string());
         let _ = smt.add(key.clone(), value.clone());
-        let result = smt.get(key.clone());
-        assert_eq!(result, Some(value));
 
-        let key2 = Key::Str("ccc".to_string());
-        let result2 = smt.get(key2.clone());
-        assert_eq!(result2, None);
+        let new_value = Value::Str("ccc".to_string());
+        let result = smt.update(key.clone(), new_value.clone());
+        assert!(result.is_ok());
+        assert_eq!(smt.nodes.len(), 1);
+        assert_eq!(
+            smt.nodes.get(&smt.root),
+            Some(&vec![
+                key.clone(),
+                new_value.clone(),
+                smt.entry_mark.clone()
+            ])
+        );
+
+        let key2 = Key::Str("def".to_string());
+        let result2 = smt.update(key2.clone(), new_value.clone());
+        assert_eq!(result2, Err(SMTError::KeyDoesNotExist(key2.to_string())));
+
+        let invalid_value = Value::Str("xyz".to_string());
+        let result3 = smt.update(key.clone(), invalid_value.clone());
+        assert_eq!(
+            result3,
+            Err(SMTError::InvalidParameterType(
+                invalid_value.to_string(),
+                "BigInt or hexadecimal string".to_string()
+            ))
+        );
+        assert_eq!(smt.get(key.clone()), Some(new_value.clone()));
 
         let mut smt = SMT::new(hash_function, true);
         let key = Key::BigInt(BigInt::from(123));
         let value = Value::BigInt(BigInt::from(456));
         let _ = smt.add(key.clone(), value.clone());
-        let result = smt.get(key.clone());
-        assert_eq!(result, Some(value));
+
+        let new_value = Value::BigInt(BigInt::from(789));
+        let result = smt.update(key.clone(), new_value.clone());
+        assert!(result.is_ok());
+        assert_eq!(smt.nodes.len(), 1);
+        assert_eq!(
+            smt.nodes.get(&smt.root),
+            Some(&vec![
+                key.clone(),
+                new_value.clone(),
+                smt.entry_mark.clone()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_upsert() {
+        let mut smt = SMT::new(hash_function, true);
+        let mut expected = SMT::new(hash_function, true);
+
+        // Insert path.
+        for i in [1, 2, 5, 12] {
+            let key = Key::BigInt(BigInt::from(i));
+            let value = Value::BigInt(BigInt::from(i * 10));
+
+            smt.upsert(key.clone(), value.clone()).unwrap();
+            expected.add(key, value).unwrap();
+
+            assert_eq!(smt.root, expected.root);
+        }
+
+        // Update path.
+        for i in [5, 1] {
+            let key = Key::BigInt(BigInt::from(i));
+            let value = Value::BigInt(BigInt::from(i * 100));
+
+            smt.upsert(key.clone(), value.clone()).unwrap();
+            expected.update(key.clone(), value.clone()).unwrap();
+
+            assert_eq!(smt.root, expected.root);
+            assert_eq!(smt.get(key), Some(value));
+        }
+
+        assert_eq!(smt.nodes, expected.nodes);
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut smt = SMT::new(hash_function, false);
+        let key = Key::Str("abc".to_string());
+        let value = Value::Str("123".to_string());
+        let _ = smt.add(key.clone(), value.clone());
+        let result = smt.delete(key.clone());
+        assert!(result.is_ok());
+        assert_eq!(smt.nodes.len(), 0);
+        assert_eq!(smt.root, smt.zero_node);
+
+        let key2 = Key::Str("def".to_string());
+        let result2 = smt.delete(key2.clone());
+        assert_eq!(result2, Err(SMTError::KeyDoesNotExist(key2.to_string())));
+
+        let mut smt = SMT::new(hash_function, true);
+        let key = Key::BigInt(BigInt::from(123));
+        let value = Value::BigInt(BigInt::from(456));
+        let _ = smt.add(key.clone(), value.clone());
+        let result = smt.delete(key.clone());
+        assert!(result.is_ok());
+        assert_eq!(smt.nodes.len(), 0);
+        assert_eq!(smt.root, smt.zero_node);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut smt = SMT::new(hash_function, false);
+        let key = [0xab; 32];
+        let value = [0, 1, 0xab, 3];
+        smt.add_bytes(&key, &value).unwrap();
+        smt.add_bytes(&[0, 1], &[]).unwrap();
+        smt.add_bytes(&[0x12], &[0xff; 40]).unwrap();
+
+        assert_eq!(smt.get_bytes(&key), Some(value.to_vec()));
+        assert_eq!(smt.get_bytes(&[0, 1]), Some(vec![]));
+        assert_eq!(smt.get_bytes(&[1]), Some(vec![]));
+        assert_eq!(smt.get_bytes(&[0x12]), Some(vec![0xff; 40]));
+        assert_eq!(smt.get_bytes(&[0x13]), None);
+        assert_eq!(
+            smt.add_bytes(&key, &value),
+            Err(SMTError::KeyAlreadyExist("ab".repeat(32)))
+        );
+
+        // The keys are the hexadecimal encoding of the bytes.
+        let key = Key::Str("ab".repeat(32));
+        assert_eq!(
+            smt.get(key.clone()),
+            Some(Value::Str("0001ab03".to_string()))
+        );
+
+        // The values are parsed as `add` does, so an encoding made of decimal digits is a number.
+        smt.add_bytes(&[0x34], &[0, 1, 2]).unwrap();
+        assert_eq!(
+            smt.get(Key::Str("34".to_string())),
+            Some(Value::BigInt(BigInt::from(102)))
+        );
+        assert_eq!(smt.get_bytes(&[0x34]), Some(vec![1, 2]));
+
+        for key in [
+            key,
+            Key::Str("0001".to_string()),
+            Key::Str("12".to_string()),
+        ] {
+            let proof = smt.create_proof(key).unwrap();
+            assert!(proof.membership);
+            assert!(smt.verify_proof(proof));
+        }
+
+        let proof = smt.create_proof(Key::Str("13".to_string())).unwrap();
+        assert!(!proof.membership);
+        assert!(smt.verify_proof(proof));
+
+        assert!(smt.add_bytes(&[], &value).is_err());
+        assert!(smt.add_bytes(&[0; 33], &value).is_err());
+        assert_eq!(smt.get_bytes(&[]), None);
+    }
+
+    #[test]
+    fn test_bytes_proofs() {
+        let keys: Vec<[u8; 32]> = (0..16u8)
+            .map(|i| {
+                let mut key = [i.wrapping_mul(37); 32];
+                key[0] = i % 3;
+                key[31] = i;
+                key
+            })
+            .collect();
+
+        for big_numbers in [false, true] {
+            let mut smt = SMT::new(hash_function, big_numbers);
+            let mut bytes_smt = SMT::new(hash_function, big_numbers);
+
+            for (i, key) in keys.iter().enumerate() {
+                let value = [i as u8, 0x10 + i as u8];
+                smt.add(Key::Str(hex::encode(key)), Value::Str(hex::encode(value)))
+                    .unwrap();
+                bytes_smt.add_bytes(key, &value).unwrap();
+            }
+            assert_eq!(smt.root, bytes_smt.root);
+
+            let mut absent_key = keys[3];
+            absent_key[16] ^= 1;
+            for key in keys.iter().chain([&absent_key, &[0xff; 32]]) {
+                let proof = smt.create_proof(Key::Str(hex::encode(key))).unwrap();
+                let bytes_proof = bytes_smt.create_proof_bytes(key).unwrap();
+                assert_eq!(proof, bytes_proof);
+                assert!(bytes_smt.verify_proof(bytes_proof));
+            }
+
+            smt.update(Key::Str(hex::encode(keys[5])), Value::Str("ff".to_string()))
+                .unwrap();
+            bytes_smt.update_bytes(&keys[5], &[0xff]).unwrap();
+            assert_eq!(smt.root, bytes_smt.root);
+            assert_eq!(bytes_smt.get_bytes(&keys[5]), Some(vec![0xff]));
+
+            for key in &keys[..8] {
+                smt.delete(Key::Str(hex::encode(key))).unwrap();
+                bytes_smt.delete_bytes(key).unwrap();
+                assert_eq!(smt.root, bytes_smt.root);
+            }
+
+            assert_eq!(
+                bytes_smt.delete_bytes(&keys[0]),
+                Err(SMTError::KeyDoesNotExist(
+                    smt.normalize_key(Key::Str(hex::encode(keys[0])))
+                        .unwrap()
+                        .to_string()
+                ))
+            );
+            assert!(bytes_smt.update_bytes(&absent_key, &[1]).is_err());
+            assert!(bytes_smt.create_proof_bytes(&[]).is_err());
+        }
+
+        // The path of a number key is derived from its bytes.
+        for number in [
+            BigInt::from(0),
+            BigInt::from(1),
+            BigInt::from(u128::MAX),
+            BigInt::from_bytes_be(Sign::Plus, &[0xab; 32]),
+        ] {
+            assert_eq!(
+                key_path(&Node::BigInt(number.clone())),
+                key_to_path(&number.to_str_radix(16))
+            );
+        }
+    }
+
+    #[test]
+    fn test_separate_values() {
+        // Unlike `hash_function`, it does not map a single node to itself.
+        fn hash_function(nodes: Vec<Node>) -> Node {
+            let strings: Vec<String> = nodes.iter().map(|node| node.to_string()).collect();
+            Node::Str(format!("[{}]", strings.join(",")))
+        }
+
+        let mut smt = SMT::new_with_separate_values(hash_function, false);
+        let key = Key::Str("aaa".to_string());
+        let value = Value::Str("bbb".to_string());
+        smt.add(key.clone(), value.clone()).unwrap();
+        smt.add(Key::Str("ccc".to_string()), Value::Str("ddd".to_string()))
+            .unwrap();
+
+        let digest = hash_function(vec![value.clone()]);
+        let leaf = vec![key.clone(), digest, smt.entry_mark.clone()];
+        assert!(smt.nodes.values().any(|child_nodes| *child_nodes == leaf));
+        assert!(!smt.nodes.values().flatten().any(|node| *node == value));
+
+        assert_eq!(smt.get(key.clone()), Some(value));
+        assert_eq!(smt.get(Key::Str("eee".to_string())), None);
+
+        let proof = smt.create_proof(key.clone()).unwrap();
+        assert_eq!(proof.entry_response.entry, leaf);
+        assert!(smt.verify_proof(proof));
+        assert!(smt.verify_proof(smt.create_proof(Key::Str("eee".to_string())).unwrap()));
+
+        let new_value = Value::Str("fff".to_string());
+        smt.update(key.clone(), new_value.clone()).unwrap();
+        assert_eq!(smt.get(key.clone()), Some(new_value));
+        assert!(smt.verify_proof(smt.create_proof(key.clone()).unwrap()));
+
+        smt.delete(key.clone()).unwrap();
+        assert_eq!(smt.get(key.clone()), None);
+        assert_eq!(smt.values.as_ref().map(|values| values.len()), Some(1));
+        assert!(smt.verify_proof(smt.create_proof(key).unwrap()));
+
+        let mut smt = SMT::builder(hash_function, true).separate_values().build();
+        let key = Key::BigInt(BigInt::from(123));
+        let value = Value::BigInt(BigInt::from(456));
+        smt.add(key.clone(), value.clone()).unwrap();
+        assert_eq!(smt.get(key.clone()), Some(value));
+        assert!(smt.verify_proof(smt.create_proof(key).unwrap()));
+    }
+
+    #[test]
+    fn test_smt_is_send_and_sync() {
+        fn assert_send_and_sync<T: Send + Sync>() {}
+        assert_send_and_sync::<SMT>();
+    }
+
+    #[test]
+    fn test_get_with_cache() {
+        let mut smt = SMT::new_with_cache(hash_function, false, 2);
+        let key = Key::Str("aaa".to_string());
+        let value = Value::Str("bbb".to_string());
+        smt.add(key.clone(), value.clone()).unwrap();
+        assert_eq!(smt.get(key.clone()), Some(value.clone()));
+        assert_eq!(smt.get(key.clone()), Some(value));
+
+        let new_value = Value::Str("ccc".to_string());
+        smt.update(key.clone(), new_value.clone()).unwrap();
+        assert_eq!(smt.get(key.clone()), Some(new_value));
+
+        smt.delete(key.clone()).unwrap();
+        assert_eq!(smt.get(key.clone()), None);
+
+        let mut smt = SMT::builder(hash_function, true).cache(2).build();
+        let key = Key::BigInt(BigInt::from(123));
+        let value = Value::BigInt(BigInt::from(456));
+        smt.add(key.clone(), value.clone()).unwrap();
+        assert_eq!(smt.get(key.clone()), Some(value));
+
+        let new_value = Value::BigInt(BigInt::from(789));
+        smt.update(key.clone(), new_value.clone()).unwrap();
+        assert_eq!(smt.get(key.clone()), Some(new_value));
+    }
+
+    #[test]
+    fn test_entry_depth() {
+        let mut smt = SMT::new(hash_function, false);
+        assert_eq!(smt.average_depth(), 0.0);
+
+        let key = Key::Str("11".to_string());
+        smt.add(key.clone(), Value::Str("a".to_string())).unwrap();
+        assert_eq!(smt.entry_depth(&key), Some(0));
+
+        // The paths of "11" and "21" share the first 4 bits, while the path of "2" diverges
+        // from both at the first bit.
+        let key2 = Key::Str("21".to_string());
+        let key3 = Key::Str("2".to_string());
+        smt.add(key2.clone(), Value::Str("b".to_string())).unwrap();
+        smt.add(key3.clone(), Value::Str("c".to_string())).unwrap();
+
+        assert_eq!(smt.entry_depth(&key), Some(5));
+        assert_eq!(smt.entry_depth(&key2), Some(5));
+        assert_eq!(smt.entry_depth(&key3), Some(1));
+        assert_eq!(smt.entry_depth(&Key::Str("9".to_string())), None);
+        assert_eq!(smt.average_depth(), 11.0 / 3.0);
+
+        let mut smt = SMT::new(hash_function, true);
+        let key = Key::BigInt(BigInt::from(1));
+        smt.add(key.clone(), Value::BigInt(BigInt::from(1)))
+            .unwrap();
+        smt.add(Key::BigInt(BigInt::from(2)), Value::BigInt(BigInt::from(2)))
+            .unwrap();
+        assert_eq!(smt.entry_depth(&key), Some(1));
+        assert_eq!(smt.average_depth(), 1.0);
+    }
+
+    #[test]
+    fn test_hash_collision() {
+        // It maps every entry to the same node.
+        fn colliding_hash_function(nodes: Vec<Node>) -> Node {
+            if nodes.len() == 3 {
+                Node::Str("entry".to_string())
+            } else {
+                hash_function(nodes)
+            }
+        }
+
+        let mut smt = SMT::new(colliding_hash_function, false);
+        smt.add(Key::Str("a".to_string()), Value::Str("b".to_string()))
+            .unwrap();
+
+        assert_eq!(
+            smt.add(Key::Str("c".to_string()), Value::Str("d".to_string())),
+            Err(SMTError::HashCollision("entry".to_string()))
+        );
+
+        // Re-computing an identical node is not a collision.
+        let mut smt = SMT::new(colliding_hash_function, false);
+        smt.add(Key::Str("a".to_string()), Value::Str("b".to_string()))
+            .unwrap();
+        smt.update(Key::Str("a".to_string()), Value::Str("b".to_string()))
+            .unwrap();
+
+        // The value of a colliding entry is not stored.
+        let mut smt = SMT::new_with_separate_values(colliding_hash_function, false);
+        smt.add(Key::Str("a".to_string()), Value::Str("b".to_string()))
+            .unwrap();
+        assert_eq!(
+            smt.add(Key::Str("c".to_string()), Value::Str("d".to_string())),
+            Err(SMTError::HashCollision("entry".to_string()))
+        );
+        assert!(!smt
+            .values
+            .as_ref()
+            .unwrap()
+            .contains_key(&Key::Str("c".to_string())));
+    }
+
+    #[test]
+    fn test_capacity_estimate() {
+        assert_eq!(SMT::capacity_estimate(256), BigInt::from(1) << 126);
+        assert_eq!(SMT::capacity_estimate(64), BigInt::from(1) << 30);
+        assert_eq!(SMT::capacity_estimate(5), BigInt::from(1));
+        assert_eq!(SMT::capacity_estimate(0), BigInt::from(1));
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut smt = SMT::new(hash_function, true);
+        let mut bloom_smts = [
+            SMT::builder(hash_function, true)
+                .bloom_filter(1024, 4)
+                .build(),
+            // A tiny filter saturates and reports false positives, but never false negatives.
+            SMT::new_with_bloom_filter(hash_function, true, 8, 2),
+        ];
+
+        let check = |smt: &SMT, bloom_smts: &[SMT]| {
+            for i in 0..200 {
+                let key = Key::BigInt(BigInt::from(i));
+                for bloom_smt in bloom_smts {
+                    assert_eq!(bloom_smt.contains_key(&key), smt.contains_key(&key));
+                }
+            }
+        };
+
+        for i in (0..100).step_by(3) {
+            let key = Key::BigInt(BigInt::from(i));
+            smt.add(key.clone(), Value::BigInt(BigInt::from(i)))
+                .unwrap();
+            for bloom_smt in &mut bloom_smts {
+                bloom_smt
+                    .add(key.clone(), Value::BigInt(BigInt::from(i)))
+                    .unwrap();
+            }
+        }
+        check(&smt, &bloom_smts);
+
+        // Keys are normalized before they are added to the filter.
+        assert!(bloom_smts[0].contains_key(&Key::Str("c".to_string())));
+
+        for i in (0..100).step_by(6) {
+            let key = Key::BigInt(BigInt::from(i));
+            smt.delete(key.clone()).unwrap();
+            for bloom_smt in &mut bloom_smts {
+                bloom_smt.delete(key.clone()).unwrap();
+            }
+        }
+        check(&smt, &bloom_smts);
+
+        for bloom_smt in &mut bloom_smts {
+            bloom_smt
+                .upsert(Key::BigInt(BigInt::from(3)), Value::BigInt(BigInt::from(4)))
+                .unwrap();
+            bloom_smt
+                .upsert(
+                    Key::BigInt(BigInt::from(150)),
+                    Value::BigInt(BigInt::from(4)),
+                )
+                .unwrap();
+            assert!(bloom_smt.contains_key(&Key::BigInt(BigInt::from(3))));
+            assert!(bloom_smt.contains_key(&Key::BigInt(BigInt::from(150))));
+
+            bloom_smt.clear();
+            assert!(!bloom_smt.contains_key(&Key::BigInt(BigInt::from(3))));
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let ops: Vec<SMTOp> = (0..40)
+            .map(|i| SMTOp::Add(Key::BigInt(BigInt::from(i)), Value::BigInt(BigInt::from(i))))
+            .chain((0..40).step_by(3).map(|i| {
+                SMTOp::Update(
+                    Key::BigInt(BigInt::from(i)),
+                    Value::BigInt(BigInt::from(100)),
+                )
+            }))
+            .chain(
+                (0..40)
+                    .step_by(4)
+                    .map(|i| SMTOp::Delete(Key::BigInt(BigInt::from(i)))),
+            )
+            .chain([
+                // A key added, updated and deleted in the same batch.
+                SMTOp::Add(Key::Str("ff".to_string()), Value::Str("a".to_string())),
+                SMTOp::Update(Key::BigInt(BigInt::from(255)), Value::Str("b".to_string())),
+                SMTOp::Delete(Key::BigInt(BigInt::from(255))),
+                SMTOp::Add(Key::BigInt(BigInt::from(4)), Value::Str("c".to_string())),
+            ])
+            .collect();
+
+        let apply_sequentially = |smt: &mut SMT, ops: &[SMTOp]| {
+            for op in ops {
+                match op.clone() {
+                    SMTOp::Add(key, value) => smt.add(key, value).unwrap(),
+                    SMTOp::Update(key, value) => smt.update(key, value).unwrap(),
+                    SMTOp::Delete(key) => smt.delete(key).unwrap(),
+                }
+            }
+        };
+
+        let new_smts = || {
+            [
+                SMT::new(hash_function, false),
+                SMT::new(hash_function, true),
+                SMT::builder(hash_function, true).separate_values().build(),
+                SMT::builder(hash_function, true)
+                    .bloom_filter(64, 2)
+                    .build(),
+            ]
+        };
+
+        for (mut smt, mut expected) in new_smts().into_iter().zip(new_smts()) {
+            // The batch is split so that the second part changes an existing tree.
+            for ops in [&ops[..25], &ops[25..]] {
+                smt.apply(ops).unwrap();
+                apply_sequentially(&mut expected, ops);
+
+                assert_eq!(smt.root, expected.root);
+                assert_eq!(smt.node_count(), expected.node_count());
+                assert_eq!(smt.audit(), Ok(()));
+            }
+
+            for i in 0..45 {
+                let key = Key::BigInt(BigInt::from(i));
+                assert_eq!(smt.get(key.clone()), expected.get(key.clone()));
+                assert_eq!(smt.contains_key(&key), expected.contains_key(&key));
+                assert!(smt.verify_proof(smt.create_proof(key).unwrap()));
+            }
+
+            // Deleting all the entries empties the tree.
+            let keys: Vec<Key> = smt
+                .iter_nodes()
+                .filter(|(_, child_nodes)| child_nodes.len() == 3)
+                .map(|(_, child_nodes)| child_nodes[0].clone())
+                .collect();
+            let ops: Vec<SMTOp> = keys.into_iter().map(SMTOp::Delete).collect();
+            smt.apply(&ops).unwrap();
+            assert_eq!(smt.root, smt.zero_node);
+            assert_eq!(smt.node_count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_apply_is_atomic() {
+        let mut smt = SMT::new(hash_function, true);
+        smt.add(Key::BigInt(BigInt::from(1)), Value::BigInt(BigInt::from(1)))
+            .unwrap();
+        let root = smt.root.clone();
+        let nodes = smt.nodes.clone();
+
+        for (ops, error) in [
+            (
+                vec![
+                    SMTOp::Add(Key::BigInt(BigInt::from(2)), Value::BigInt(BigInt::from(2))),
+                    SMTOp::Add(Key::BigInt(BigInt::from(1)), Value::BigInt(BigInt::from(3))),
+                ],
+                SMTError::KeyAlreadyExist("1".to_string()),
+            ),
+            (
+                vec![
+                    SMTOp::Delete(Key::BigInt(BigInt::from(1))),
+                    SMTOp::Update(Key::BigInt(BigInt::from(1)), Value::BigInt(BigInt::from(3))),
+                ],
+                SMTError::KeyDoesNotExist("1".to_string()),
+            ),
+            (
+                vec![SMTOp::Delete(Key::BigInt(BigInt::from(2)))],
+                SMTError::KeyDoesNotExist("2".to_string()),
+            ),
+        ] {
+            assert_eq!(smt.apply(&ops), Err(error));
+            assert_eq!(smt.root, root);
+            assert_eq!(smt.nodes, nodes);
+        }
+
+        smt.apply(&[]).unwrap();
+        assert_eq!(smt.root, root);
+    }
+
+    #[test]
+    fn test_clear() {
+        let smts = [
+            SMT::new(hash_function, false),
+            SMT::builder(hash_function, false).cache(4).build(),
+            SMT::builder(hash_function, false).separate_values().build(),
+        ];
+
+        for mut smt in smts {
+            let key = Key::Str("a".to_string());
+            smt.add(key.clone(), Value::Str("b".to_string())).unwrap();
+            smt.add(Key::Str("c".to_string()), Value::Str("d".to_string()))
+                .unwrap();
+            assert_eq!(smt.get(key.clone()), Some(Value::Str("b".to_string())));
+            let root = smt.root.clone();
+
+            smt.clear();
+            assert_eq!(smt.get(key.clone()), None);
+            assert_eq!(smt.root, smt.zero_node);
+            assert_eq!(smt.node_count(), 0);
+
+            smt.add(key.clone(), Value::Str("e".to_string())).unwrap();
+            assert_eq!(smt.get(key.clone()), Some(Value::Str("e".to_string())));
+            smt.update(key.clone(), Value::Str("b".to_string()))
+                .unwrap();
+            smt.add(Key::Str("c".to_string()), Value::Str("d".to_string()))
+                .unwrap();
+            assert_eq!(smt.root, root);
+        }
+    }
+
+    #[test]
+    fn test_deep_matching_prefix() {
+        let one = BigInt::from(1);
+        // The first two keys agree on the first 250 bits of their paths and differ at bit 251,
+        // the third one shares all of them and the fourth one differs only at the last bit.
+        let keys = [
+            BigInt::from(5),
+            BigInt::from(5) + (&one << 250),
+            BigInt::from(5) + (&one << 250) + (&one << 251),
+            BigInt::from(5) + (&one << 255),
+        ];
+        let absent_keys = [
+            BigInt::from(5) + (&one << 251),
+            BigInt::from(5) + (&one << 250) + (&one << 254),
+            BigInt::from(4),
+        ];
+
+        for big_numbers in [false, true] {
+            let mut smt = SMT::new(hash_function, big_numbers);
+
+            for (i, key) in keys.iter().enumerate() {
+                smt.add(Key::BigInt(key.clone()), Value::BigInt(BigInt::from(i)))
+                    .unwrap();
+                assert_eq!(smt.audit(), Ok(()));
+
+                for (j, key) in keys.iter().enumerate() {
+                    let proof = smt.create_proof(Key::BigInt(key.clone())).unwrap();
+                    assert_eq!(proof.membership, j <= i);
+                    assert!(smt.verify_proof(proof));
+                }
+            }
+
+            assert_eq!(smt.entry_depth(&Key::BigInt(keys[0].clone())), Some(256));
+            assert_eq!(smt.entry_depth(&Key::BigInt(keys[1].clone())), Some(252));
+            assert_eq!(smt.entry_depth(&Key::BigInt(keys[2].clone())), Some(252));
+            assert_eq!(smt.entry_depth(&Key::BigInt(keys[3].clone())), Some(256));
+
+            for key in &absent_keys {
+                let proof = smt.create_proof(Key::BigInt(key.clone())).unwrap();
+                assert!(!proof.membership);
+                assert!(smt.verify_proof(proof));
+            }
+
+            for key in &keys {
+                smt.delete(Key::BigInt(key.clone())).unwrap();
+                assert_eq!(smt.audit(), Ok(()));
+            }
+            assert_eq!(smt.root, smt.zero_node);
+            assert_eq!(smt.node_count(), 0);
+        }
     }
+
     #[test]
-    fn test_add() {
-        let mut smt = SMT::new(hash_function, false);
-        let key = Key::Str("aaa".to_string());
-        let value = Value::Str("bbb".to_string());
-        let result = smt.add(key.clone(), value.clone());
-        assert!(result.is_ok());
-        assert_eq!(smt.nodes.len(), 1);
+    fn test_audit() {
+        assert_eq!(SMT::new(hash_function, true).audit(), Ok(()));
+
+        let new_smt = || {
+            let mut smt = SMT::new(hash_function, true);
+            for i in [1, 2, 5, 9, 12] {
+                smt.add(Key::BigInt(BigInt::from(i)), Value::BigInt(BigInt::from(i)))
+                    .unwrap();
+            }
+            smt.delete(Key::BigInt(BigInt::from(2))).unwrap();
+            smt
+        };
+        let smt = new_smt();
+        assert_eq!(smt.audit(), Ok(()));
+
+        // A corrupted internal node.
+        let mut corrupted_smt = new_smt();
+        let internal_node = corrupted_smt
+            .nodes
+            .iter()
+            .find(|(node, child_nodes)| child_nodes.len() == 2 && **node != smt.root)
+            .map(|(node, _)| node.clone())
+            .unwrap();
+        corrupted_smt.nodes.get_mut(&internal_node).unwrap()[0] = Node::BigInt(BigInt::from(7));
         assert_eq!(
-            smt.nodes.get(&smt.root),
-            Some(&vec![key.clone(), value.clone(), smt.entry_mark.clone()])
+            corrupted_smt.audit(),
+            Err(SMTError::CorruptNode(internal_node.to_string()))
         );
 
-        let mut smt = SMT::new(hash_function, true);
-        let key = Key::BigInt(BigInt::from(123));
-        let value = Value::BigInt(BigInt::from(456));
-        let result = smt.add(key.clone(), value.clone());
-        assert!(result.is_ok());
-        assert_eq!(smt.nodes.len(), 1);
+        // A corrupted entry.
+        let mut corrupted_smt = new_smt();
+        let leaf = hash_function(vec![
+            Node::BigInt(BigInt::from(5)),
+            Node::BigInt(BigInt::from(5)),
+            Node::BigInt(BigInt::from(1)),
+        ]);
+        corrupted_smt.nodes.get_mut(&leaf).unwrap()[1] = Node::BigInt(BigInt::from(6));
         assert_eq!(
-            smt.nodes.get(&smt.root),
-            Some(&vec![key.clone(), value.clone(), smt.entry_mark.clone()])
+            corrupted_smt.audit(),
+            Err(SMTError::CorruptNode(leaf.to_string()))
         );
-    }
 
-    #[test]
-    fn test_update() {
-        let mut smt = SMT::new(hash_function, false);
-        let key = Key::Str("aaa".to_string());
-        let value = Value::Str("bbb".to_string());
-        let _ = smt.add(key.clone(), value.clone());
-
-        let new_value = Value::Str("ccc".to_string());
-        let result = smt.update(key.clone(), new_value.clone());
-        assert!(result.is_ok());
-        assert_eq!(smt.nodes.len(), 1);
+        // A missing node.
+        let mut corrupted_smt = new_smt();
+        corrupted_smt.nodes.remove(&leaf);
         assert_eq!(
-            smt.nodes.get(&smt.root),
-            Some(&vec![
-                key.clone(),
-                new_value.clone(),
-                smt.entry_mark.clone()
-            ])
+            corrupted_smt.audit(),
+            Err(SMTError::CorruptNode(leaf.to_string()))
         );
 
-        let key2 = Key::Str("def".to_string());
-        let result2 = smt.update(key2.clone(), new_value.clone());
-        assert_eq!(result2, Err(SMTError::KeyDoesNotExist(key2.to_string())));
+        // An unreachable node.
+        let mut corrupted_smt = new_smt();
+        let node = Node::Str("1,2".to_string());
+        corrupted_smt.nodes.insert(
+            node.clone(),
+            vec![Node::BigInt(BigInt::from(1)), Node::BigInt(BigInt::from(2))],
+        );
+        assert_eq!(
+            corrupted_smt.audit(),
+            Err(SMTError::CorruptNode(node.to_string()))
+        );
+    }
 
+    #[test]
+    fn test_keys_with_prefix() {
         let mut smt = SMT::new(hash_function, true);
-        let key = Key::BigInt(BigInt::from(123));
-        let value = Value::BigInt(BigInt::from(456));
-        let _ = smt.add(key.clone(), value.clone());
+        assert!(smt.keys_with_prefix(&[]).is_empty());
 
-        let new_value = Value::BigInt(BigInt::from(789));
-        let result = smt.update(key.clone(), new_value.clone());
-        assert!(result.is_ok());
-        assert_eq!(smt.nodes.len(), 1);
-        assert_eq!(
-            smt.nodes.get(&smt.root),
-            Some(&vec![
-                key.clone(),
-                new_value.clone(),
-                smt.entry_mark.clone()
-            ])
-        );
+        for i in [2, 4, 6, 7, 9] {
+            smt.add(Key::BigInt(BigInt::from(i)), Value::BigInt(BigInt::from(i)))
+                .unwrap();
+        }
+
+        let keys = |prefix_bits: &[usize]| -> Vec<i32> {
+            let mut keys: Vec<i32> = smt
+                .keys_with_prefix(prefix_bits)
+                .iter()
+                .map(|key| key.to_string().parse().unwrap())
+                .collect();
+            keys.sort();
+            keys
+        };
+
+        assert_eq!(keys(&[]), vec![2, 4, 6, 7, 9]);
+        assert_eq!(keys(&[0]), vec![2, 4, 6]);
+        assert_eq!(keys(&[1]), vec![7, 9]);
+        assert_eq!(keys(&[0, 1]), vec![2, 6]);
+        assert_eq!(keys(&[0, 0]), vec![4]);
+        // The prefix goes past the entries, which are higher in the tree.
+        assert_eq!(keys(&[1, 1, 1]), vec![7]);
+        assert_eq!(keys(&[1, 0, 0, 1]), vec![9]);
+        assert_eq!(keys(&[1, 0, 1]), Vec::<i32>::new());
+        assert_eq!(keys(&[0, 0, 1, 1]), Vec::<i32>::new());
+
+        // A prefix longer than the paths matches no key, even if its first bits match a path.
+        let mut prefix_bits = vec![0; PATH_LENGTH];
+        prefix_bits[..3].copy_from_slice(&[0, 0, 1]);
+        assert_eq!(keys(&prefix_bits), vec![4]);
+        prefix_bits.push(1);
+        assert_eq!(keys(&prefix_bits), Vec::<i32>::new());
     }
 
     #[test]
-    fn test_delete() {
+    fn test_root_bytes() {
+        let mut smt = SMT::new(hash_function, true);
+        assert_eq!(smt.root_bytes(), Ok([0; 32]));
+
+        smt.root = Node::BigInt(BigInt::from(0x1234));
+        let mut expected = [0; 32];
+        expected[30..].copy_from_slice(&[0x12, 0x34]);
+        assert_eq!(smt.root_bytes(), Ok(expected));
+
+        smt.root = Node::BigInt(BigInt::from(-1));
+        assert!(smt.root_bytes().is_err());
+
+        smt.root = Node::BigInt(BigInt::from(1) << 256);
+        assert!(smt.root_bytes().is_err());
+
         let mut smt = SMT::new(hash_function, false);
-        let key = Key::Str("abc".to_string());
-        let value = Value::Str("123".to_string());
-        let _ = smt.add(key.clone(), value.clone());
-        let result = smt.delete(key.clone());
-        assert!(result.is_ok());
-        assert_eq!(smt.nodes.len(), 0);
-        assert_eq!(smt.root, smt.zero_node);
+        assert_eq!(smt.root_bytes(), Ok([0; 32]));
 
-        let key2 = Key::Str("def".to_string());
-        let result2 = smt.delete(key2.clone());
-        assert_eq!(result2, Err(SMTError::KeyDoesNotExist(key2.to_string())));
+        smt.root = Node::Str("ab".repeat(32));
+        assert_eq!(smt.root_bytes(), Ok([0xab; 32]));
+
+        smt.root = Node::Str("1234".to_string());
+        assert_eq!(smt.root_bytes(), Ok(expected));
+
+        smt.root = Node::Str("1".repeat(65));
+        assert!(smt.root_bytes().is_err());
+
+        // The comma separated roots of `hash_function` are not hexadecimal.
+        let mut smt = SMT::new(hash_function, false);
+        smt.add(Key::Str("a".to_string()), Value::Str("b".to_string()))
+            .unwrap();
+        assert!(smt.root_bytes().is_err());
+    }
 
+    #[test]
+    fn test_iter_nodes() {
         let mut smt = SMT::new(hash_function, true);
-        let key = Key::BigInt(BigInt::from(123));
-        let value = Value::BigInt(BigInt::from(456));
-        let _ = smt.add(key.clone(), value.clone());
-        let result = smt.delete(key.clone());
-        assert!(result.is_ok());
-        assert_eq!(smt.nodes.len(), 0);
-        assert_eq!(smt.root, smt.zero_node);
+        assert_eq!(smt.iter_nodes().count(), 0);
+        assert_eq!(smt.node_count(), 0);
+
+        for i in 1..8 {
+            smt.add(Key::BigInt(BigInt::from(i)), Value::BigInt(BigInt::from(i)))
+                .unwrap();
+        }
+        smt.delete(Key::BigInt(BigInt::from(3))).unwrap();
+        smt.delete(Key::BigInt(BigInt::from(6))).unwrap();
+
+        let nodes: Vec<(Node, Vec<Node>)> = smt.iter_nodes().collect();
+        let (leaves, internal_nodes): (Vec<_>, Vec<_>) = nodes
+            .iter()
+            .partition(|(_, child_nodes)| child_nodes.len() == 3);
+
+        assert_eq!(nodes.len(), smt.node_count());
+        assert_eq!(leaves.len(), 5);
+        assert_eq!(internal_nodes.len() + leaves.len(), smt.node_count());
+        assert!(internal_nodes
+            .iter()
+            .all(|(node, child_nodes)| hash_function(child_nodes.clone()) == *node));
+        assert!(nodes.iter().any(|(node, _)| *node == smt.root));
+
+        // The export is sorted.
+        let hashes: Vec<String> = nodes.iter().map(|(node, _)| node.to_string()).collect();
+        let mut sorted_hashes = hashes.clone();
+        sorted_hashes.sort();
+        assert_eq!(hashes, sorted_hashes);
     }
 
     #[test]
@@ -728,14 +3722,14 @@ mod tests {
         let key = Key::Str("abc".to_string());
         let value = Value::Str("123".to_string());
         let _ = smt.add(key.clone(), value.clone());
-        let proof = smt.create_proof(key.clone());
+        let proof = smt.create_proof(key.clone()).unwrap();
         assert_eq!(proof.root, smt.root);
 
         let mut smt = SMT::new(hash_function, true);
         let key = Key::BigInt(BigInt::from(123));
         let value = Value::BigInt(BigInt::from(456));
         let _ = smt.add(key.clone(), value.clone());
-        let proof = smt.create_proof(key.clone());
+        let proof = smt.create_proof(key.clone()).unwrap();
         assert_eq!(proof.root, smt.root);
     }
 
@@ -745,7 +3739,7 @@ mod tests {
         let key = Key::Str("abc".to_string());
         let value = Value::Str("123".to_string());
         let _ = smt.add(key.clone(), value.clone());
-        let proof = smt.create_proof(key.clone());
+        let proof = smt.create_proof(key.clone()).unwrap();
         let result = smt.verify_proof(proof);
         assert!(result);
 
@@ -758,6 +3752,7 @@ mod tests {
             },
             root: smt.root.clone(),
             membership: false,
+            zero_siblings: Vec::new(),
         };
         let fun = smt.verify_proof(false_proof);
         assert!(!fun);
@@ -766,7 +3761,7 @@ mod tests {
         let key = Key::BigInt(BigInt::from(123));
         let value = Value::BigInt(BigInt::from(456));
         let _ = smt.add(key.clone(), value.clone());
-        let proof = smt.create_proof(key.clone());
+        let proof = smt.create_proof(key.clone()).unwrap();
         let result = smt.verify_proof(proof);
         assert!(result);
 
@@ -779,11 +3774,478 @@ mod tests {
             },
             root: smt.root.clone(),
             membership: true,
+            zero_siblings: Vec::new(),
         };
         let fun = smt.verify_proof(false_proof);
         assert!(!fun);
     }
 
+    #[test]
+    fn test_verify_proof_current() {
+        let mut smt = SMT::new(hash_function, false);
+        smt.add(Key::Str("2".to_string()), Value::Str("a".to_string()))
+            .unwrap();
+
+        let membership_proof = smt.create_proof(Key::Str("2".to_string())).unwrap();
+        let non_membership_proof = smt.create_proof(Key::Str("5".to_string())).unwrap();
+        assert!(smt.verify_proof_current(membership_proof.clone()));
+        assert!(smt.verify_proof_current(non_membership_proof.clone()));
+
+        smt.add(Key::Str("5".to_string()), Value::Str("b".to_string()))
+            .unwrap();
+
+        // The stale proofs are still valid for the old root, but not for the current one.
+        assert!(smt.verify_proof(membership_proof.clone()));
+        assert!(!smt.verify_proof_current(membership_proof));
+        assert!(!smt.verify_proof_current(non_membership_proof));
+
+        let membership_proof = smt.create_proof(Key::Str("2".to_string())).unwrap();
+        assert!(smt.verify_proof_current(membership_proof));
+    }
+
+    #[test]
+    fn test_verify_against_root() {
+        let mut smt = SMT::new(hash_function, true);
+        for i in [1, 2, 5, 12] {
+            smt.add(
+                Key::BigInt(BigInt::from(i)),
+                Value::BigInt(BigInt::from(i * 2)),
+            )
+            .unwrap();
+        }
+
+        let mut proofs: Vec<MerkleProof> = (0..8)
+            .map(|i| smt.create_proof(Key::BigInt(BigInt::from(i))).unwrap())
+            .collect();
+        let root = smt.root.clone();
+        drop(smt);
+
+        assert!(proofs.iter().any(|proof| !proof.membership));
+        assert_eq!(
+            SMT::verify_against_root(&root, SMT::builder(hash_function, true), &proofs),
+            vec![true; 8]
+        );
+
+        // A tampered value, a proof for another root, and a proof for the wrong mode.
+        proofs[1].entry_response.entry[1] = Value::BigInt(BigInt::from(3));
+        proofs[5] = proofs[5].clone().compress(&Node::BigInt(BigInt::from(0)));
+        let mut other_smt = SMT::new(hash_function, true);
+        other_smt
+            .add(Key::BigInt(BigInt::from(6)), Value::BigInt(BigInt::from(6)))
+            .unwrap();
+        proofs[6] = other_smt
+            .create_proof(Key::BigInt(BigInt::from(6)))
+            .unwrap();
+
+        assert_eq!(
+            SMT::verify_against_root(&root, SMT::builder(hash_function, true), &proofs),
+            vec![true, false, true, true, true, true, false, true]
+        );
+        assert_eq!(
+            SMT::verify_against_root(&root, SMT::builder(hash_function, false), &proofs[..1]),
+            vec![false]
+        );
+    }
+
+    #[test]
+    fn test_verify_against_root_custom_nodes() {
+        let zero_node = Node::BigInt(BigInt::from(7));
+        let entry_mark = Node::BigInt(BigInt::from(9));
+        let builder = SMT::builder(hash_function, true)
+            .zero_node(zero_node.clone())
+            .entry_mark(entry_mark);
+        let mut smt = builder.clone().build();
+        for i in [1, 2, 5] {
+            smt.add(
+                Key::BigInt(BigInt::from(i)),
+                Value::BigInt(BigInt::from(i * 2)),
+            )
+            .unwrap();
+        }
+
+        let proofs: Vec<MerkleProof> = [1, 3]
+            .iter()
+            .map(|i| smt.create_proof(Key::BigInt(BigInt::from(*i))).unwrap())
+            .collect();
+
+        assert_eq!(
+            SMT::verify_against_root(&smt.root, builder, &proofs),
+            vec![true, true]
+        );
+        assert_eq!(
+            SMT::verify_against_root(&smt.root, SMT::builder(hash_function, true), &proofs),
+            vec![false, false]
+        );
+        assert_eq!(
+            SMT::verify_against_root(
+                &smt.root,
+                SMT::builder(hash_function, true).zero_node(zero_node),
+                &proofs[..1]
+            ),
+            vec![false]
+        );
+    }
+
+    #[test]
+    fn test_multiple_entries_root() {
+        // The siblings are placed on the side given by the path bits, up to the root: with the
+        // paths 1 -> [1, 0, ..], 2 -> [0, 1, ..] and 3 -> [1, 1, ..], the entry 2 is on the left
+        // of the root, and the entries 1 and 3 are on the left and on the right of its sibling.
+        let mut smt = SMT::new(hash_function, true);
+        for i in 1..=3 {
+            smt.add(
+                Key::BigInt(BigInt::from(i)),
+                Value::BigInt(BigInt::from(i * 10)),
+            )
+            .unwrap();
+        }
+        assert_eq!(smt.root, Node::Str("2,20,1,1,10,1,3,30,1".to_string()));
+
+        smt.delete(Key::BigInt(BigInt::from(2))).unwrap();
+        assert_eq!(smt.root, Node::Str("0,1,10,1,3,30,1".to_string()));
+
+        smt.delete(Key::BigInt(BigInt::from(3))).unwrap();
+        assert_eq!(smt.root, Node::Str("1,10,1".to_string()));
+    }
+
+    #[test]
+    fn test_add_and_delete_multiple_keys() {
+        let keys: Vec<Key> = (1..10).map(|i| Key::BigInt(BigInt::from(i))).collect();
+
+        let mut smt = SMT::new(hash_function, true);
+        for key in &keys {
+            smt.add(key.clone(), key.clone()).unwrap();
+        }
+
+        for key in &keys {
+            assert_eq!(smt.get(key.clone()), Some(key.clone()));
+            assert!(smt.verify_proof(smt.create_proof(key.clone()).unwrap()));
+        }
+
+        for key in &keys[..5] {
+            smt.delete(key.clone()).unwrap();
+        }
+
+        let mut expected = SMT::new(hash_function, true);
+        for key in &keys[5..] {
+            expected.add(key.clone(), key.clone()).unwrap();
+        }
+
+        assert_eq!(smt.root, expected.root);
+        assert_eq!(smt.nodes, expected.nodes);
+
+        for key in &keys[5..] {
+            assert!(smt.verify_proof(smt.create_proof(key.clone()).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_compress_proof() {
+        let mut smt = SMT::new(hash_function, false);
+        // The paths of the two keys share the first 4 bits.
+        smt.add(Key::Str("11".to_string()), Value::Str("a".to_string()))
+            .unwrap();
+        smt.add(Key::Str("21".to_string()), Value::Str("b".to_string()))
+            .unwrap();
+
+        // The path of the key leads to a zero node and its siblings are all zero nodes but one.
+        let proof = smt.create_proof(Key::Str("9".to_string())).unwrap();
+        assert!(!proof.membership);
+        assert_eq!(proof.entry_response.matching_entry, None);
+        assert_eq!(proof.entry_response.siblings.len(), 4);
+
+        let compressed = proof.clone().compress(&smt.zero_node);
+        assert_eq!(compressed.entry_response.siblings.len(), 1);
+        assert_eq!(compressed.zero_siblings, vec![0, 1, 2]);
+        assert!(smt.verify_proof(compressed.clone()));
+        assert_eq!(compressed.decompress(&smt.zero_node), proof);
+
+        let proof = smt.create_proof(Key::Str("11".to_string())).unwrap();
+        let compressed = proof.clone().compress(&smt.zero_node);
+        assert_eq!(compressed.entry_response.siblings.len(), 1);
+        assert!(smt.verify_proof(compressed.clone()));
+        assert_eq!(compressed.clone().compress(&smt.zero_node), compressed);
+        assert_eq!(compressed.decompress(&smt.zero_node), proof);
+    }
+
+    #[test]
+    fn test_path_bits() {
+        let mut smt = SMT::new(hash_function, true);
+        for i in [1, 2, 5, 12, 28] {
+            smt.add(Key::BigInt(BigInt::from(i)), Value::BigInt(BigInt::from(i)))
+                .unwrap();
+        }
+
+        for i in 0..32 {
+            let proof = smt.create_proof(Key::BigInt(BigInt::from(i))).unwrap();
+            let EntryResponse {
+                entry,
+                matching_entry,
+                siblings,
+            } = &proof.entry_response;
+
+            let path_bits = proof.path_bits();
+            assert_eq!(path_bits.len(), siblings.len());
+
+            // The root is reconstructed from the bits only, without the path of the key.
+            let mut node = match (proof.membership, matching_entry) {
+                (true, _) => hash_function(entry.clone()),
+                (false, Some(matching_entry)) => hash_function(matching_entry.clone()),
+                (false, None) => smt.zero_node.clone(),
+            };
+            for (sibling, bit) in siblings.iter().zip(&path_bits).rev() {
+                node = if *bit == 1 {
+                    hash_function(vec![sibling.clone(), node])
+                } else {
+                    hash_function(vec![node, sibling.clone()])
+                };
+            }
+            assert_eq!(node, smt.root);
+
+            // The bits do not depend on the compression of the proof.
+            assert_eq!(
+                proof.clone().compress(&smt.zero_node).path_bits(),
+                path_bits
+            );
+        }
+    }
+
+    #[test]
+    fn test_proof_size() {
+        let mut smt = SMT::new(hash_function, true);
+        for i in [1, 2, 9] {
+            smt.add(
+                Key::BigInt(BigInt::from(i)),
+                Value::BigInt(BigInt::from(i * 10)),
+            )
+            .unwrap();
+        }
+
+        // The only sibling of 2 is the subtree of 1 and 9.
+        let proof = smt.create_proof(Key::BigInt(BigInt::from(2))).unwrap();
+        let size: usize = proof
+            .entry_response
+            .entry
+            .iter()
+            .chain(&proof.entry_response.siblings)
+            .map(|node| node.to_string().len())
+            .sum();
+        assert_eq!(proof.size_bytes(), size);
+        assert!(proof.is_minimal(&smt.zero_node));
+        assert_eq!(proof.clone().compress(&smt.zero_node), proof);
+
+        // 1 and 9 share their first 3 bits, so their proofs are padded with zero siblings.
+        let membership_proof = smt.create_proof(Key::BigInt(BigInt::from(1))).unwrap();
+        assert_eq!(membership_proof.entry_response.siblings.len(), 4);
+        assert!(!membership_proof.is_minimal(&smt.zero_node));
+
+        // The path of 5 leads to a zero node next to the subtree of 1 and 9.
+        let non_membership_proof = smt.create_proof(Key::BigInt(BigInt::from(5))).unwrap();
+        assert!(!non_membership_proof.membership);
+        assert!(!non_membership_proof.is_minimal(&smt.zero_node));
+
+        for proof in [membership_proof, non_membership_proof] {
+            let compressed = proof.clone().compress(&smt.zero_node);
+            let zero_siblings = compressed.zero_siblings.len();
+
+            assert!(zero_siblings > 0);
+            assert!(compressed.is_minimal(&smt.zero_node));
+            // Each zero node takes 1 byte, as its removed position.
+            assert_eq!(compressed.size_bytes(), proof.size_bytes());
+            assert!(smt.verify_proof(compressed));
+        }
+
+        // With a wider zero node, compression saves space.
+        let zero_node = Node::BigInt(BigInt::from(1) << 128);
+        let mut proof = smt.create_proof(Key::BigInt(BigInt::from(5))).unwrap();
+        proof.entry_response.siblings = vec![zero_node.clone(); 4];
+        let compressed = proof.clone().compress(&zero_node);
+        assert!(!proof.is_minimal(&zero_node));
+        assert!(compressed.is_minimal(&zero_node));
+        assert_eq!(proof.size_bytes(), compressed.size_bytes() + 4 * 39 - 4);
+    }
+
+    #[test]
+    fn test_verify_tampered_proofs() {
+        let mut smt = SMT::new(hash_function, true);
+        for i in [1, 2, 3, 5, 8] {
+            smt.add(Key::BigInt(BigInt::from(i)), Value::BigInt(BigInt::from(i)))
+                .unwrap();
+        }
+
+        // A membership proof with a flipped sibling.
+        let proof = smt.create_proof(Key::BigInt(BigInt::from(3))).unwrap();
+        assert!(smt.verify_proof(proof.clone()));
+        let mut tampered = proof.clone();
+        tampered.entry_response.siblings[0] = Node::BigInt(BigInt::from(42));
+        assert!(!smt.verify_proof(tampered));
+
+        // A membership proof turned into a non-membership proof of the same key.
+        let mut tampered = proof.clone();
+        tampered.entry_response.matching_entry = Some(proof.entry_response.entry.clone());
+        tampered.entry_response.entry.truncate(1);
+        tampered.membership = false;
+        assert!(!smt.verify_proof(tampered));
+
+        // A membership proof with the membership flag unset.
+        let mut tampered = proof.clone();
+        tampered.membership = false;
+        assert!(!smt.verify_proof(tampered));
+
+        // A non-membership proof with a matching entry.
+        let proof = smt.create_proof(Key::BigInt(BigInt::from(7))).unwrap();
+        assert!(proof.entry_response.matching_entry.is_some());
+        assert!(smt.verify_proof(proof.clone()));
+
+        // A non-membership proof claiming membership.
+        let mut tampered = proof.clone();
+        tampered.membership = true;
+        assert!(!smt.verify_proof(tampered));
+
+        // A non-membership proof whose matching entry is swapped for another real entry.
+        let other_entry = smt
+            .create_proof(Key::BigInt(BigInt::from(2)))
+            .unwrap()
+            .entry_response
+            .entry;
+        let mut tampered = proof.clone();
+        tampered.entry_response.matching_entry = Some(other_entry);
+        assert!(!smt.verify_proof(tampered));
+
+        // A non-membership proof padded with extra zero siblings.
+        let mut tampered = proof.clone();
+        tampered.entry_response.siblings.push(smt.zero_node.clone());
+        assert!(!smt.verify_proof(tampered));
+        let mut tampered = proof.clone();
+        tampered
+            .entry_response
+            .siblings
+            .insert(0, smt.zero_node.clone());
+        assert!(!smt.verify_proof(tampered));
+
+        // A non-membership proof whose matching entry is an internal node.
+        let mut tampered = proof.clone();
+        let internal_node = smt.nodes.get(&smt.root).unwrap().clone();
+        tampered.entry_response.matching_entry = Some(internal_node);
+        tampered.entry_response.siblings = Vec::new();
+        assert!(!smt.verify_proof(tampered));
+    }
+
+    #[test]
+    fn test_verify_malformed_proofs() {
+        let mut smt = SMT::new(hash_function, false);
+        for key in ["a3", "b4", "c5"] {
+            smt.add(Key::Str(key.to_string()), Value::Str("1f".to_string()))
+                .unwrap();
+        }
+
+        let proof = smt.create_proof(Key::Str("b4".to_string())).unwrap();
+        let non_membership_proof = smt.create_proof(Key::Str("d6".to_string())).unwrap();
+        assert!(non_membership_proof.entry_response.matching_entry.is_some());
+
+        // More siblings than the length of the paths.
+        let mut tampered = proof.clone();
+        tampered.entry_response.siblings = vec![Node::Str("2e".to_string()); 300];
+        assert!(!smt.verify_proof(tampered.clone()));
+        assert!(tampered.path_bits().is_empty());
+        assert_eq!(
+            SMT::verify_against_root(&smt.root, SMT::builder(hash_function, false), &[tampered]),
+            vec![false]
+        );
+
+        // Compressed zero siblings pushing the number of siblings over the length of the paths.
+        let mut tampered = proof.clone().compress(&smt.zero_node);
+        tampered.zero_siblings = (0..256).collect();
+        assert!(!smt.verify_proof(tampered));
+
+        // An entry key that is not a hexadecimal string.
+        for key in ["xyz", "", "-a3"] {
+            let mut tampered = proof.clone();
+            tampered.entry_response.entry[0] = Key::Str(key.to_string());
+            assert!(!smt.verify_proof(tampered.clone()));
+            assert!(tampered.path_bits().is_empty());
+
+            let mut tampered = non_membership_proof.clone();
+            tampered.entry_response.entry[0] = Key::Str(key.to_string());
+            assert!(!smt.verify_proof(tampered));
+        }
+
+        // A matching entry key that is not a hexadecimal string.
+        let mut tampered = non_membership_proof.clone();
+        tampered.entry_response.matching_entry.as_mut().unwrap()[0] = Key::Str("xyz".to_string());
+        assert!(!smt.verify_proof(tampered.clone()));
+        assert!(tampered.path_bits().is_empty());
+
+        // A negative entry key.
+        let mut tampered = non_membership_proof.clone();
+        tampered.entry_response.entry[0] = Key::BigInt(BigInt::from(-5));
+        assert!(!smt.verify_proof(tampered));
+
+        // A batch proof with a malformed key or too many siblings.
+        let batch_proof = smt
+            .create_non_membership_batch(&[Key::Str("d6".to_string())])
+            .unwrap();
+        assert!(smt.verify_non_membership_batch(&batch_proof));
+
+        let mut tampered = batch_proof.clone();
+        tampered.entries[0].key = Key::Str("xyz".to_string());
+        assert!(!smt.verify_non_membership_batch(&tampered));
+
+        let mut tampered = batch_proof.clone();
+        tampered.entries[0].siblings = vec![0; 300];
+        assert!(!smt.verify_non_membership_batch(&tampered));
+    }
+
+    #[test]
+    fn test_non_membership_batch() {
+        let mut smt = SMT::new(hash_function, true);
+        for i in [1, 2, 3, 5, 8] {
+            smt.add(Key::BigInt(BigInt::from(i)), Value::BigInt(BigInt::from(i)))
+                .unwrap();
+        }
+
+        let keys: Vec<Key> = [4, 7, 11]
+            .into_iter()
+            .map(|i| Key::BigInt(BigInt::from(i)))
+            .collect();
+        let batch_proof = smt.create_non_membership_batch(&keys).unwrap();
+        assert_eq!(batch_proof.entries.len(), 3);
+        assert!(smt.verify_non_membership_batch(&batch_proof));
+
+        // The siblings shared by the paths are stored once.
+        let sibling_count: usize = keys
+            .iter()
+            .map(|key| {
+                smt.create_proof(key.clone())
+                    .unwrap()
+                    .entry_response
+                    .siblings
+                    .len()
+            })
+            .sum();
+        assert!(batch_proof.siblings.len() < sibling_count);
+
+        let keys: Vec<Key> = [4, 5, 11]
+            .into_iter()
+            .map(|i| Key::BigInt(BigInt::from(i)))
+            .collect();
+        assert_eq!(
+            smt.create_non_membership_batch(&keys),
+            Err(SMTError::KeyAlreadyExist("5".to_string()))
+        );
+
+        // A batch proof for a key that exists does not verify.
+        let mut tampered = batch_proof.clone();
+        tampered.entries[1].key = Key::BigInt(BigInt::from(5));
+        assert!(!smt.verify_non_membership_batch(&tampered));
+
+        let mut tampered = batch_proof.clone();
+        tampered.entries[0]
+            .siblings
+            .push(batch_proof.siblings.len());
+        assert!(!smt.verify_non_membership_batch(&tampered));
+    }
+
     #[test]
     fn test_retrieve_entry() {
         let smt = SMT::new(hash_function, false);
@@ -841,12 +4303,18 @@ mod tests {
         let new_node = smt
             .add_new_nodes(node.clone(), path, &siblings, None)
             .unwrap();
-        assert_eq!(new_node, Node::Str("sibling2,sibling3,node".to_string()));
+        assert_eq!(
+            new_node,
+            Node::Str("sibling2,node,sibling3,sibling1".to_string())
+        );
 
         let starting_index = smt
             .add_new_nodes(node.clone(), path, &siblings, Some(1))
             .unwrap();
-        assert_eq!(starting_index, Node::Str("sibling2,node".to_string()));
+        assert_eq!(
+            starting_index,
+            Node::Str("sibling2,node,sibling1".to_string())
+        );
 
         let mut smt = SMT::new(hash_function, true);
         let node = Node::BigInt(BigInt::from(111));
@@ -859,12 +4327,12 @@ mod tests {
         let new_node = smt
             .add_new_nodes(node.clone(), path, &siblings, None)
             .unwrap();
-        assert_eq!(new_node, Node::Str("333,444,111".to_string()));
+        assert_eq!(new_node, Node::Str("222,111,444,333".to_string()));
 
         let starting_index = smt
             .add_new_nodes(node.clone(), path, &siblings, Some(1))
             .unwrap();
-        assert_eq!(starting_index, Node::Str("333,111".to_string()));
+        assert_eq!(starting_index, Node::Str("222,111,333".to_string()));
     }
 
     #[test]
@@ -880,7 +4348,10 @@ mod tests {
         let new_node = smt
             .add_new_nodes(node.clone(), path, &siblings, None)
             .unwrap();
-        assert_eq!(new_node, Node::Str("sibling2,sibling3,abc".to_string()));
+        assert_eq!(
+            new_node,
+            Node::Str("sibling2,abc,sibling3,sibling1".to_string())
+        );
         smt.delete_old_nodes(node.clone(), path, &siblings);
         assert_eq!(smt.nodes.len(), 0);
 
@@ -894,7 +4365,7 @@ mod tests {
         let new_node = smt
             .add_new_nodes(node.clone(), path, &siblings, None)
             .unwrap();
-        assert_eq!(new_node, Node::Str("789,123".to_string()));
+        assert_eq!(new_node, Node::Str("456,123,789".to_string()));
         smt.delete_old_nodes(node.clone(), path, &siblings);
         assert_eq!(smt.nodes.len(), 0);
     }
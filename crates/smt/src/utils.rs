@@ -49,18 +49,46 @@ pub fn key_to_path(key: &str) -> Vec<usize> {
     bits_array
 }
 
+/// Converts a big-endian byte key to a path represented as a vector of usize.
+///
+/// The path is the same as the one returned by `key_to_path` for the hexadecimal encoding of the
+/// bytes, but it is derived from the bits of the bytes without any string conversion.
+///
+/// # Arguments
+///
+/// * `bytes` - The key to convert, at most 32 bytes long.
+///
+/// # Returns
+///
+/// The path represented as a vector of usize.
+pub fn bytes_to_path(bytes: &[u8]) -> Vec<usize> {
+    let mut path = vec![0; 256];
+
+    for (i, byte) in bytes.iter().rev().enumerate() {
+        for bit in 0..8 {
+            path[i * 8 + bit] = ((byte >> bit) & 1) as usize;
+        }
+    }
+
+    path
+}
+
 /// Returns the index of the last non-zero element in the array.
 ///
+/// An element is considered zero if it is equal to `zero`, so hashes of any width or encoding
+/// are handled as well.
+///
 /// # Arguments
 ///
 /// * `array` - The array of hexadecimal strings.
+/// * `zero` - The string representation of the zero node.
 ///
 /// # Returns
 ///
 /// The index of the last non-zero element in the array, or -1 if no non-zero element is found.
-pub fn get_index_of_last_non_zero_element(array: Vec<&str>) -> isize {
+pub fn get_index_of_last_non_zero_element(array: Vec<&str>, zero: &str) -> isize {
     for (i, &item) in array.iter().enumerate().rev() {
-        if u128::from_str_radix(item, 16).unwrap_or(0) != 0 {
+        if item != zero {
             return i as isize;
         }
     }
@@ -123,38 +151,75 @@ mod tests {
         assert_eq!(&path[0..5], vec![1, 1, 1, 0, 1]);
     }
 
+    #[test]
+    fn test_bytes_to_path() {
+        let path = bytes_to_path(&[0x17]);
+        assert_eq!(path.len(), 256);
+        assert_eq!(path, key_to_path("17"));
+
+        let key = [0xab; 32];
+        assert_eq!(bytes_to_path(&key), key_to_path(&"ab".repeat(32)));
+        assert_eq!(bytes_to_path(&[0, 1, 2]), key_to_path("000102"));
+    }
+
     #[test]
     fn test_get_index_of_last_non_zero_element() {
-        assert_eq!(get_index_of_last_non_zero_element(vec![]), -1);
-        assert_eq!(get_index_of_last_non_zero_element(vec!["0", "0", "0"]), -1);
+        assert_eq!(get_index_of_last_non_zero_element(vec![], "0"), -1);
+        assert_eq!(
+            get_index_of_last_non_zero_element(vec!["0", "0", "0"], "0"),
+            -1
+        );
 
-        assert_eq!(get_index_of_last_non_zero_element(vec!["0", "0", "1"]), 2);
-        assert_eq!(get_index_of_last_non_zero_element(vec!["0", "1", "0"]), 1);
-        assert_eq!(get_index_of_last_non_zero_element(vec!["1", "0", "0"]), 0);
+        assert_eq!(
+            get_index_of_last_non_zero_element(vec!["0", "0", "1"], "0"),
+            2
+        );
+        assert_eq!(
+            get_index_of_last_non_zero_element(vec!["0", "1", "0"], "0"),
+            1
+        );
+        assert_eq!(
+            get_index_of_last_non_zero_element(vec!["1", "0", "0"], "0"),
+            0
+        );
 
         assert_eq!(
-            get_index_of_last_non_zero_element(vec!["0", "1", "0", "1", "0"]),
+            get_index_of_last_non_zero_element(vec!["0", "1", "0", "1", "0"], "0"),
             3
         );
         assert_eq!(
-            get_index_of_last_non_zero_element(vec!["1", "0", "1", "0", "0"]),
+            get_index_of_last_non_zero_element(vec!["1", "0", "1", "0", "0"], "0"),
             2
         );
         assert_eq!(
-            get_index_of_last_non_zero_element(vec!["0", "0", "0", "1", "1"]),
+            get_index_of_last_non_zero_element(vec!["0", "0", "0", "1", "1"], "0"),
             4
         );
         assert_eq!(
-            get_index_of_last_non_zero_element(vec![
-                "0", "17", "3", "0", "3", "0", "3", "2", "0", "0"
-            ]),
+            get_index_of_last_non_zero_element(
+                vec!["0", "17", "3", "0", "3", "0", "3", "2", "0", "0"],
+                "0"
+            ),
             7
-        )
+        );
+
+        let wide_hash = "f".repeat(64);
+        assert_eq!(
+            get_index_of_last_non_zero_element(vec![&wide_hash, "0", "0"], "0"),
+            0
+        );
+        assert_eq!(
+            get_index_of_last_non_zero_element(vec!["0", "5", "5"], "5"),
+            0
+        );
     }
 
     #[test]
     fn test_get_first_common_elements() {
-        assert_eq!(get_first_common_elements::<u32>(&[], &[]), vec![]);
+        assert_eq!(
+            get_first_common_elements::<u32>(&[], &[]),
+            Vec::<u32>::new()
+        );
 
         assert_eq!(
             get_first_common_elements(&[1, 2, 3], &[1, 2, 3, 4, 5]),
@@ -169,7 +234,10 @@ mod tests {
             get_first_common_elements(&[1, 2, 3], &[1, 2, 4]),
             vec![1, 2]
         );
-        assert_eq!(get_first_common_elements(&[1, 2, 3], &[4, 5, 6]), vec![]);
+        assert_eq!(
+            get_first_common_elements(&[1, 2, 3], &[4, 5, 6]),
+            Vec::<i32>::new()
+        );
     }
 
     #[test]